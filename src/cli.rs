@@ -1,53 +1,931 @@
-use crate::{config::TaskConfig, errors::{CronyError, Result}, worker, Commands};
-use tracing::info;
+use crate::{
+    config::{self, task_mut, TaskConfig},
+    crontab,
+    errors::{CronyError, Result},
+    export::{self, Export},
+    history::{self, RunRecord},
+    lint::{self, Severity},
+    service::{self, Installed, Scope},
+    state::SchedulerState,
+    task::{normalize_schedule, Task, TaskOutcome},
+    tui, worker, Commands, ConfigCommand, DeleteArgs, ExportCommand, ExportCrontabArgs,
+    ExportSystemdArgs, ImportCommand, ImportCrontabArgs, ListArgs, MoveArgs, RenameArgs,
+    RestoreArgs, RunArgs, ServiceArgs, ServiceChangeArgs, ServiceCommand, ShowArgs, StatusArgs,
+    TagArgs, TasksArgs, ValidateArgs,
+};
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use console::{measure_text_width, pad_str, style, Alignment, Term};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{IsTerminal, Read},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    thread,
+};
+use tracing::{error, info, warn};
 
 pub fn handle_cli_command(command: Commands) -> Result<()> {
+    match command {
+        Commands::Config(command) => return handle_config_command(command),
+        Commands::Validate(args) => return handle_validate_command(args),
+        Commands::Service(command) => return handle_service_command(command),
+        _ => {}
+    }
+
     let config = TaskConfig::load()?;
 
     match command {
-        Commands::Run => handle_run_command(config),
-        Commands::List => handle_list_command(config),
+        Commands::Run(args) => handle_run_command(config, args),
+        Commands::List(args) => handle_list_command(config, args),
+        Commands::Show(args) => handle_show_command(config, args),
+        Commands::Status(args) => handle_status_command(config, args),
+        Commands::Tui => tui::run(config),
+        Commands::Move(args) => handle_move_command(config, args),
+        Commands::Pause(args) => handle_pause_command(config, args, true),
+        Commands::Resume(args) => handle_pause_command(config, args, false),
+        Commands::Trigger(args) => handle_trigger_command(config, args),
+        Commands::Tag(args) => handle_tag_command(config, args),
+        Commands::Rename(args) => handle_rename_command(config, args),
+        Commands::Duplicate(args) => handle_duplicate_command(config, args),
+        Commands::Delete(args) => handle_delete_command(config, args),
+        Commands::Undo => handle_undo_command(config),
+        Commands::Restore(args) => handle_restore_command(config, args),
+        Commands::Import(ImportCommand::Crontab(args)) => {
+            handle_import_crontab_command(config, args)
+        }
+        Commands::Export(ExportCommand::Crontab(args)) => {
+            handle_export_crontab_command(config, args)
+        }
+        Commands::Export(ExportCommand::Systemd(args)) => {
+            handle_export_systemd_command(config, args)
+        }
+        Commands::Config(_) | Commands::Validate(_) | Commands::Service(_) => {
+            unreachable!("handled before loading the config")
+        }
         Commands::Create => Err(CronyError::Cli(
             "Interactive create mode not supported via CLI. Use 'crony' without arguments.".into(),
         )),
-        Commands::Delete => Err(CronyError::Cli(
-            "Interactive delete mode not supported via CLI. Use 'crony' without arguments.".into(),
-        )),
         Commands::Edit => Err(CronyError::Cli(
             "Interactive edit mode not supported via CLI. Use 'crony' without arguments.".into(),
         )),
     }
 }
 
-fn handle_run_command(config: TaskConfig) -> Result<()> {
-    tracing_subscriber::fmt::init();
+fn handle_config_command(command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Path => println!("{}", config::get_config_path()?.display()),
+        ConfigCommand::Schema => {
+            let schema = schemars::schema_for!(TaskConfig);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+    }
+    Ok(())
+}
 
+fn handle_validate_command(args: ValidateArgs) -> Result<()> {
+    let issues = lint::lint()?;
+    for issue in &issues {
+        println!("{issue}");
+    }
+
+    let errors = issues
+        .iter()
+        .filter(|issue| args.strict || issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        return Err(CronyError::Config(format!(
+            "found {errors} error(s) and {warnings} warning(s)"
+        )));
+    }
+    if warnings > 0 {
+        println!("found {warnings} warning(s)");
+    } else {
+        println!("{} is valid", config::get_config_path()?.display());
+    }
+    Ok(())
+}
+
+fn handle_service_command(command: ServiceCommand) -> Result<()> {
+    match command {
+        ServiceCommand::Install(args) => handle_service_install_command(args),
+        ServiceCommand::Uninstall(args) => handle_service_uninstall_command(args),
+        ServiceCommand::Status(args) => handle_service_status_command(args),
+    }
+}
+
+/// The scope asked for and the directory its unit lives in.
+fn service_location(args: &ServiceArgs) -> Result<(Scope, PathBuf)> {
+    let scope = if args.system {
+        Scope::System
+    } else {
+        Scope::User
+    };
+    let dir = match &args.dir {
+        Some(dir) => dir.clone(),
+        None => scope.unit_dir()?,
+    };
+    Ok((scope, dir))
+}
+
+/// Runs the systemctl `commands` with `--now`, or else tells the user to.
+fn run_or_print_systemctl(commands: &[Vec<String>], now: bool) -> Result<()> {
+    if now {
+        for command in commands {
+            println!("running {}", command.join(" "));
+            service::run(command)?;
+        }
+    } else {
+        let commands: Vec<String> = commands.iter().map(|command| command.join(" ")).collect();
+        println!("run: {}", commands.join(" && "));
+    }
+    Ok(())
+}
+
+fn handle_service_install_command(args: ServiceChangeArgs) -> Result<()> {
+    let (scope, dir) = service_location(&args.service)?;
+    let path = dir.join(service::UNIT_NAME);
+    match service::install(&dir, &service::unit(scope)?)? {
+        Installed::Created => println!("installed {}", path.display()),
+        Installed::Updated => println!("updated {}", path.display()),
+        Installed::Unchanged => println!("{} is already up to date", path.display()),
+    }
+    run_or_print_systemctl(
+        &[
+            scope.systemctl(&["daemon-reload"]),
+            scope.systemctl(&["enable", "--now", service::UNIT_NAME]),
+        ],
+        args.now,
+    )
+}
+
+fn handle_service_uninstall_command(args: ServiceChangeArgs) -> Result<()> {
+    let (scope, dir) = service_location(&args.service)?;
+    let path = dir.join(service::UNIT_NAME);
+    if args.now && path.exists() {
+        run_or_print_systemctl(
+            &[scope.systemctl(&["disable", "--now", service::UNIT_NAME])],
+            true,
+        )?;
+    }
+    if !service::uninstall(&dir, scope)? {
+        println!("{} isn't installed", path.display());
+        return Ok(());
+    }
+    println!("removed {}", path.display());
+
+    let mut commands = vec![scope.systemctl(&["daemon-reload"])];
+    if !args.now {
+        commands.insert(0, scope.systemctl(&["stop", service::UNIT_NAME]));
+    }
+    run_or_print_systemctl(&commands, args.now)
+}
+
+fn handle_service_status_command(args: ServiceArgs) -> Result<()> {
+    let (scope, dir) = service_location(&args)?;
+    let path = dir.join(service::UNIT_NAME);
+    match fs::read_to_string(&path) {
+        Ok(current) if current == service::unit(scope)? => {
+            println!(
+                "unit {} {}",
+                path.display(),
+                style("installed").green().bold()
+            )
+        }
+        Ok(_) => println!(
+            "unit {} {}, run 'crony service install' to point it at this binary and config",
+            path.display(),
+            style("out of date").yellow().bold()
+        ),
+        Err(_) => println!(
+            "unit {} {}",
+            path.display(),
+            style("not installed").red().bold()
+        ),
+    }
+
+    match (
+        service::query(scope, "is-enabled"),
+        service::query(scope, "is-active"),
+    ) {
+        (Some(enabled), Some(active)) => println!("service {enabled}, {active}"),
+        _ => println!("service state unknown, systemctl didn't answer"),
+    }
+    Ok(())
+}
+
+fn handle_run_command(mut config: TaskConfig, args: RunArgs) -> Result<()> {
     if config.is_empty() {
         info!("No tasks to run");
         return Ok(());
     }
 
+    let problems = config.problems();
+    if !problems.is_empty() && !args.lenient {
+        for problem in &problems {
+            error!("{}", problem.error.message());
+        }
+        return Err(CronyError::Config(format!(
+            "found {} problem(s), fix them or use --lenient to run the other tasks",
+            problems.len()
+        )));
+    }
+
+    let disabled = config.disable(problems);
+    if !disabled.is_empty() {
+        warn!(
+            "{} task(s) are disabled because of config problems, run `crony validate` for details:",
+            disabled.len()
+        );
+        for (name, reason) in &disabled {
+            warn!("  {name}: {reason}");
+        }
+    }
+
     info!("Starting {} task(s) in background...", config.len());
 
     let rt = tokio::runtime::Runtime::new()
         .map_err(|e| CronyError::Task(format!("Failed to create runtime: {}", e)))?;
 
     rt.block_on(async {
-        worker::run_all_tasks(config.tasks).await;
+        worker::run_all_tasks(config, args.metrics, disabled).await;
     });
 
     Ok(())
 }
 
-fn handle_list_command(config: TaskConfig) -> Result<()> {
-    if config.is_empty() {
-        println!("no tasks configured");
+fn handle_move_command(mut config: TaskConfig, args: MoveArgs) -> Result<()> {
+    let name = config.resolve(&args.task)?.to_string();
+    config.move_task(&name, args.position)?;
+    println!("moved task '{name}' to position {}", args.position);
+    Ok(())
+}
+
+fn handle_delete_command(mut config: TaskConfig, args: DeleteArgs) -> Result<()> {
+    let names = config.resolve_all(&args.tasks)?;
+    if !args.yes {
+        if !std::io::stdin().is_terminal() {
+            return Err(CronyError::Cli(
+                "Refusing to delete without confirmation, pass --yes to skip it".into(),
+            ));
+        }
+        let confirmed = cliclack::confirm(format!("delete {}?", names.join(", ")))
+            .initial_value(false)
+            .interact()?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+
+    config.modify(|tasks| {
+        for name in &names {
+            tasks.shift_remove(name);
+        }
+        Ok(())
+    })?;
+    println!(
+        "deleted {}, run 'crony undo' to bring them back",
+        names.join(", ")
+    );
+    Ok(())
+}
+
+fn handle_undo_command(mut config: TaskConfig) -> Result<()> {
+    let batch = config.undo()?;
+    println!("undid the last change: {}", batch.summary());
+    Ok(())
+}
+
+fn handle_restore_command(mut config: TaskConfig, args: RestoreArgs) -> Result<()> {
+    config.restore(&args.task)?;
+    println!("restored task '{}'", args.task);
+    Ok(())
+}
+
+fn handle_import_crontab_command(mut config: TaskConfig, args: ImportCrontabArgs) -> Result<()> {
+    let (label, content) = match &args.file {
+        None => {
+            let output = process::Command::new("crontab").arg("-l").output()?;
+            if !output.status.success() {
+                return Err(CronyError::Cli(format!(
+                    "'crontab -l' failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            (
+                "crontab".to_string(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            )
+        }
+        Some(path) if path == Path::new("-") => {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content)?;
+            ("stdin".to_string(), content)
+        }
+        Some(path) => (path.display().to_string(), fs::read_to_string(path)?),
+    };
+    let system = args.system
+        || args.file.as_ref().is_some_and(|path| {
+            path == Path::new("/etc/crontab") || path.starts_with("/etc/cron.d")
+        });
+
+    let local_timezone = iana_time_zone::get_timezone()
+        .ok()
+        .filter(|name| name.parse::<Tz>().is_ok());
+    let import = crontab::parse(&content, system, local_timezone.as_deref());
+    let mut warnings: Vec<_> = import
+        .skipped
+        .iter()
+        .map(|(line, reason)| (line, format!("skipped, {reason}")))
+        .chain(import.notes.iter().map(|(line, note)| (line, note.clone())))
+        .collect();
+    warnings.sort_by_key(|(line, _)| **line);
+    for (line, warning) in warnings {
+        eprintln!(
+            "{}: {label}:{line}: {warning}",
+            style("warning").yellow().bold()
+        );
+    }
+
+    if args.dry_run {
+        #[derive(Serialize)]
+        struct Imported {
+            tasks: IndexMap<String, Task>,
+        }
+
+        let mut tasks = IndexMap::new();
+        for mut task in import.tasks {
+            task.name = unique_name(&task.name, |name| {
+                config.tasks.contains_key(name) || tasks.contains_key(name)
+            });
+            tasks.insert(task.name.clone(), task);
+        }
+        print!("{}", toml::to_string_pretty(&Imported { tasks })?);
+        return Ok(());
+    }
+
+    let mut names = Vec::new();
+    config.modify(|tasks| {
+        for mut task in import.tasks {
+            task.name = unique_name(&task.name, |name| tasks.contains_key(name));
+            names.push(task.name.clone());
+            tasks.insert(task.name.clone(), task);
+        }
+        Ok(())
+    })?;
+    println!(
+        "imported {} task(s) from {label}, skipped {} line(s)",
+        names.len(),
+        import.skipped.len()
+    );
+    if !names.is_empty() {
+        println!("added {}", names.join(", "));
+    }
+    Ok(())
+}
+
+/// The named tasks, or all of them, with their defaults filled in.
+fn tasks_to_export(config: &TaskConfig, references: &[String]) -> Result<Vec<Task>> {
+    let names = match references {
+        [] => config.tasks.keys().cloned().collect(),
+        references => config.resolve_all(references)?,
+    };
+    Ok(names
+        .iter()
+        .map(|name| config.tasks[name].effective())
+        .collect())
+}
+
+/// Prints what didn't export exactly to stderr, so it stays out of the
+/// exported files when stdout is redirected.
+fn print_export_warnings(export: &Export) {
+    for warning in &export.warnings {
+        eprintln!("{}: {warning}", style("warning").yellow().bold());
+    }
+}
+
+fn handle_export_crontab_command(config: TaskConfig, args: ExportCrontabArgs) -> Result<()> {
+    let export = export::crontab(&tasks_to_export(&config, &args.tasks)?)?;
+    print_export_warnings(&export);
+    for (_, content) in &export.files {
+        print!("{content}");
+    }
+    Ok(())
+}
+
+fn handle_export_systemd_command(config: TaskConfig, args: ExportSystemdArgs) -> Result<()> {
+    let export = export::systemd(&tasks_to_export(&config, &args.tasks)?)?;
+    print_export_warnings(&export);
+
+    let Some(dir) = &args.dir else {
+        for (i, (name, content)) in export.files.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("# {name}\n{content}");
+        }
+        return Ok(());
+    };
+
+    fs::create_dir_all(dir)?;
+    for (name, content) in &export.files {
+        config::write_file(&dir.join(name), content)?;
+    }
+    let timers: Vec<&str> = export
+        .files
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .filter(|name| name.ends_with(".timer"))
+        .collect();
+    println!(
+        "wrote {} unit file(s) to {}",
+        export.files.len(),
+        dir.display()
+    );
+    if !timers.is_empty() {
+        let user = match std::env::var_os("HOME") {
+            Some(home) if dir.starts_with(&home) => " --user",
+            _ => "",
+        };
+        println!(
+            "start them with: systemctl{user} daemon-reload && systemctl{user} enable --now {}",
+            timers.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// `name`, or `name-2`, `name-3` and so on if it is taken.
+fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => name.to_string(),
+            n => format!("{name}-{n}"),
+        })
+        .find(|candidate| !taken(candidate))
+        .expect("some suffix is free")
+}
+
+fn handle_rename_command(mut config: TaskConfig, args: RenameArgs) -> Result<()> {
+    let name = config.resolve(&args.task)?.to_string();
+    config.rename_task(&name, &args.new_name)?;
+    println!("renamed task '{name}' to '{}'", args.new_name);
+    Ok(())
+}
+
+fn handle_duplicate_command(mut config: TaskConfig, args: RenameArgs) -> Result<()> {
+    let name = config.resolve(&args.task)?.to_string();
+    config.duplicate_task(&name, &args.new_name)?;
+    println!("duplicated task '{name}' as '{}'", args.new_name);
+    Ok(())
+}
+
+fn handle_pause_command(mut config: TaskConfig, args: TasksArgs, paused: bool) -> Result<()> {
+    let names = config.resolve_all(&args.tasks)?;
+    config.modify(|tasks| {
+        for name in &names {
+            task_mut(tasks, name)?.paused = paused;
+        }
+        Ok(())
+    })?;
+
+    let action = if paused { "paused" } else { "resumed" };
+    println!("{action} {}", names.join(", "));
+    Ok(())
+}
+
+fn handle_trigger_command(config: TaskConfig, args: TasksArgs) -> Result<()> {
+    let names = config.resolve_all(&args.tasks)?;
+    let tasks: Vec<_> = names
+        .iter()
+        .map(|name| config.tasks[name.as_str()].clone())
+        .collect();
+    if let Some(task) = tasks.iter().find(|task| task.paused) {
+        return Err(CronyError::Task(format!(
+            "Task '{}' is paused, resume it first",
+            task.name
+        )));
+    }
+
+    let mut failed = 0;
+    for (name, result) in worker::trigger(tasks)? {
+        match result {
+            Ok(record) if record.outcome == TaskOutcome::Success => {
+                println!("task '{name}' finished: {}", record.outcome);
+            }
+            Ok(record) => {
+                failed += 1;
+                println!("task '{name}' finished: {}", record.outcome);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("task '{name}' could not be recorded: {e}");
+            }
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        n => Err(CronyError::Task(format!(
+            "{n} triggered task(s) did not succeed"
+        ))),
+    }
+}
+
+fn handle_tag_command(mut config: TaskConfig, args: TagArgs) -> Result<()> {
+    let names = config.resolve_all(&args.tasks)?;
+    config.modify(|tasks| {
+        for name in &names {
+            let tags = &mut task_mut(tasks, name)?.tags;
+            if args.remove {
+                tags.retain(|tag| *tag != args.tag);
+            } else if !tags.contains(&args.tag) {
+                tags.push(args.tag.clone());
+            }
+        }
+        Ok(())
+    })?;
+
+    let action = if args.remove { "removed" } else { "added" };
+    println!("{action} tag '{}' on {}", args.tag, names.join(", "));
+    Ok(())
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ListFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+/// A `--filter` condition on `crony list`, written as `tag=<tag>` or
+/// `status=<success|failure|timeout|never>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TaskFilter {
+    Tag(String),
+    Status(Option<TaskOutcome>),
+}
+
+impl FromStr for TaskFilter {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input.split_once('=') {
+            Some(("tag", tag)) => Ok(TaskFilter::Tag(tag.to_string())),
+            Some(("status", "success")) => Ok(TaskFilter::Status(Some(TaskOutcome::Success))),
+            Some(("status", "failure")) => Ok(TaskFilter::Status(Some(TaskOutcome::Failure))),
+            Some(("status", "timeout")) => Ok(TaskFilter::Status(Some(TaskOutcome::Timeout))),
+            Some(("status", "never")) => Ok(TaskFilter::Status(None)),
+            Some(("status", status)) => Err(format!(
+                "unknown status '{status}', expected success, failure, timeout or never"
+            )),
+            _ => Err(format!(
+                "invalid filter '{input}', expected tag=<tag> or status=<status>"
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TaskRow {
+    index: usize,
+    name: String,
+    schedule: String,
+    cron: Option<String>,
+    enabled: bool,
+    tags: Vec<String>,
+    command: String,
+    last_run: Option<DateTime<Local>>,
+    last_status: Option<TaskOutcome>,
+    next_run: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+impl TaskRow {
+    const HEADERS: [&'static str; 10] = [
+        "index",
+        "name",
+        "schedule",
+        "cron",
+        "enabled",
+        "tags",
+        "last_run",
+        "last_status",
+        "next_run",
+        "command",
+    ];
+
+    fn headers(show_source: bool) -> Vec<&'static str> {
+        let mut headers = Self::HEADERS.to_vec();
+        if show_source {
+            headers.push("source");
+        }
+        headers
+    }
+
+    fn new(index: usize, task: &Task, source: Option<String>) -> Result<Self> {
+        let last = history::last(&task.name)?;
+        Ok(Self {
+            index,
+            name: task.name.clone(),
+            schedule: task.schedule.clone(),
+            cron: normalize_schedule(&task.schedule).ok(),
+            enabled: !task.paused,
+            tags: task.tags.clone(),
+            command: task.command_summary(),
+            last_run: last.as_ref().map(|run| run.started_at),
+            last_status: last.map(|run| run.outcome),
+            next_run: task.next_run(),
+            source,
+        })
+    }
+
+    fn matches(&self, filter: &TaskFilter) -> bool {
+        match filter {
+            TaskFilter::Tag(tag) => self.tags.contains(tag),
+            TaskFilter::Status(status) => self.last_status == *status,
+        }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        let time = |t: &Option<DateTime<Local>>| {
+            t.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default()
+        };
+        let mut cells = vec![
+            self.index.to_string(),
+            self.name.clone(),
+            self.schedule.clone(),
+            self.cron.clone().unwrap_or_default(),
+            self.enabled.to_string(),
+            self.tags.join(","),
+            time(&self.last_run),
+            self.last_status.map(|s| s.to_string()).unwrap_or_default(),
+            time(&self.next_run),
+            self.command.clone(),
+        ];
+        cells.extend(self.source.clone());
+        cells
+    }
+}
+
+fn handle_list_command(config: TaskConfig, args: ListArgs) -> Result<()> {
+    let config_path = config::get_config_path()?;
+    let mut rows = Vec::new();
+    for (i, task) in config.tasks.values().enumerate() {
+        let source = args.show_source.then(|| {
+            let path = config.source(&task.name).unwrap_or(&config_path);
+            path.display().to_string()
+        });
+        let row = TaskRow::new(i + 1, task, source)?;
+        if args.filter.iter().all(|filter| row.matches(filter)) {
+            rows.push(row);
+        }
+    }
+
+    match args.format {
+        ListFormat::Table if config.is_empty() => println!("no tasks configured"),
+        ListFormat::Table if rows.is_empty() => println!("no tasks match the filter"),
+        ListFormat::Table => print_table(
+            &TaskRow::headers(args.show_source)
+                .iter()
+                .map(|h| h.replace('_', " ").to_uppercase())
+                .collect::<Vec<_>>(),
+            &rows.iter().map(TaskRow::cells).collect::<Vec<_>>(),
+        ),
+        ListFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+        ListFormat::Yaml => print!(
+            "{}",
+            serde_yaml::to_string(&rows).map_err(|e| CronyError::Cli(e.to_string()))?
+        ),
+        ListFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            let csv_error = |e: csv::Error| CronyError::Cli(e.to_string());
+            writer
+                .write_record(TaskRow::headers(args.show_source))
+                .map_err(csv_error)?;
+            for row in &rows {
+                writer.write_record(row.cells()).map_err(csv_error)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_show_command(config: TaskConfig, args: ShowArgs) -> Result<()> {
+    #[derive(Serialize)]
+    struct Shown<'a> {
+        tasks: BTreeMap<&'a str, Task>,
+    }
+
+    let name = config.resolve(&args.task)?;
+    let task = &config.tasks[name];
+    let task = if args.effective {
+        task.effective()
     } else {
-        println!("configured tasks:");
-        for (i, (name, task)) in config.tasks.iter().enumerate() {
-            println!("{}. {name} | {} | {}", i + 1, task.schedule, task.command);
+        task.clone()
+    };
+
+    let source = match config.source(name) {
+        Some(source) => source.to_path_buf(),
+        None => config::get_config_path()?,
+    };
+    println!("# {}", source.display());
+    let shown = Shown {
+        tasks: BTreeMap::from([(name, task)]),
+    };
+    print!("{}", toml::to_string_pretty(&shown)?);
+    Ok(())
+}
+
+fn print_table(header: &[String], rows: &[Vec<String>]) {
+    let widths: Vec<_> = (0..header.len())
+        .map(|col| {
+            rows.iter()
+                .map(Vec::as_slice)
+                .chain([header])
+                .map(|row| measure_text_width(&row[col]))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| pad_str(cell, *width, Alignment::Left, None))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", line(header));
+    for row in rows {
+        println!("{}", line(row));
+    }
+}
+
+/// What a task is doing right now, as shown by `crony status` and the TUI.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskState {
+    Idle,
+    Running,
+    Paused,
+    Failed,
+    /// Left out by the running scheduler because of config problems.
+    Disabled,
+}
+
+impl TaskState {
+    pub fn new(task: &Task, last: Option<&RunRecord>, running: bool, disabled: bool) -> Self {
+        match last {
+            _ if disabled => TaskState::Disabled,
+            _ if task.paused => TaskState::Paused,
+            _ if running => TaskState::Running,
+            Some(run) if run.outcome != TaskOutcome::Success => TaskState::Failed,
+            _ => TaskState::Idle,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskState::Idle => "idle",
+            TaskState::Running => "running",
+            TaskState::Paused => "paused",
+            TaskState::Failed => "failed",
+            TaskState::Disabled => "disabled",
+        }
+    }
+
+    fn styled(self) -> String {
+        let label = style(self.label());
+        match self {
+            TaskState::Idle => label.green(),
+            TaskState::Running => label.cyan(),
+            TaskState::Paused => label.yellow(),
+            TaskState::Failed => label.red(),
+            TaskState::Disabled => label.magenta(),
         }
+        .bold()
+        .to_string()
+    }
+}
+
+fn handle_status_command(mut config: TaskConfig, args: StatusArgs) -> Result<()> {
+    if !args.watch {
+        return print_status(&config);
+    }
+
+    let term = Term::stdout();
+    loop {
+        term.clear_screen()?;
+        print_status(&config)?;
+        thread::sleep(std::time::Duration::from_secs(1));
+        config = TaskConfig::load()?;
+    }
+}
+
+fn print_status(config: &TaskConfig) -> Result<()> {
+    let now = Local::now();
+    // A state file that can't be read is treated like a scheduler that
+    // isn't running, as in the TUI.
+    let scheduler = SchedulerState::load()
+        .ok()
+        .flatten()
+        .filter(SchedulerState::is_live);
+
+    match &scheduler {
+        Some(state) => println!(
+            "scheduler {} (pid {}, up {})",
+            style("running").green().bold(),
+            state.pid,
+            format_duration(now - state.started_at)
+        ),
+        None => println!("scheduler {}", style("not running").red().bold()),
+    }
+
+    if config.is_empty() {
+        println!("no tasks configured");
+        return Ok(());
     }
 
+    let mut rows = Vec::new();
+    for task in config.tasks.values() {
+        let last = history::last(&task.name)?;
+        let running = scheduler
+            .as_ref()
+            .and_then(|state| state.running(&task.name));
+        let disabled = scheduler
+            .as_ref()
+            .is_some_and(|state| state.disabled.contains_key(&task.name));
+        let state = TaskState::new(task, last.as_ref(), running.is_some(), disabled);
+
+        rows.push(vec![
+            task.name.clone(),
+            state.styled(),
+            running
+                .map(|run| format_duration(now - run.started_at))
+                .unwrap_or_default(),
+            last.as_ref()
+                .and_then(|run| run.exit_code())
+                .map(|code| code.to_string())
+                .unwrap_or_default(),
+            last.as_ref()
+                .map(|run| run.started_at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            task.next_run()
+                .filter(|_| scheduler.is_some())
+                .map(|next| format!("in {}", format_duration(next - now)))
+                .unwrap_or_default(),
+        ]);
+    }
+
+    let header = [
+        "TASK",
+        "STATE",
+        "RUNNING FOR",
+        "LAST EXIT",
+        "LAST RUN",
+        "NEXT RUN",
+    ];
+    print_table(&header.map(String::from), &rows);
+
+    let disabled = scheduler.iter().flat_map(|state| &state.disabled);
+    for (i, (name, reason)) in disabled.enumerate() {
+        if i == 0 {
+            println!("\ndisabled because of config problems:");
+        }
+        println!("  {}: {reason}", style(name).bold());
+    }
     Ok(())
 }
+
+/// Formats a duration as its two most significant units, e.g. `1h 5m`.
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    let (days, hours, minutes, seconds) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}s"),
+        (0, 0, _) => format!("{minutes}m {seconds}s"),
+        (0, _, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
@@ -1,17 +1,88 @@
 use crate::errors::{CronyError, Result};
-use crate::task::Task;
+use crate::history;
+use crate::migrate::{self, CONFIG_VERSION};
+use crate::notify::Notifier;
+use crate::task::{Task, TaskDefaults};
+use crate::trash::{Batch, Trash};
+use crate::webhook::Webhook;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
 
-#[derive(Serialize, Deserialize, Default)]
+/// Something wrong with a task, found by [`TaskConfig::problems`].
+#[derive(Debug)]
+pub struct Problem {
+    pub task: String,
+    /// The key in the task's table the problem is with, if it's about a
+    /// single setting.
+    pub key: Option<&'static str>,
+    pub error: CronyError,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct TaskConfig {
-    pub tasks: HashMap<String, Task>,
+    /// The format version of the file. Older files are upgraded when loaded.
+    #[serde(default)]
+    pub version: u32,
+    /// More config files to read tasks from, relative to this one. Only
+    /// allowed in the main config file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<PathBuf>,
+    /// Settings used by every task that doesn't set them itself. Only allowed
+    /// in the main config file.
+    #[serde(default, skip_serializing_if = "TaskDefaults::is_empty")]
+    pub defaults: TaskDefaults,
+    /// Tasks in the order they appear in the file, which is also the order
+    /// they are listed and numbered in.
+    #[serde(default)]
+    pub tasks: IndexMap<String, Task>,
+    /// Notifiers in the order they appear in the files.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub notifiers: IndexMap<String, Notifier>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+    /// The file each task was read from, for tasks that aren't in the main
+    /// config file.
+    #[serde(skip)]
+    sources: HashMap<String, PathBuf>,
 }
 
 impl TaskConfig {
+    /// Reads the main config file, then the files it includes in the order
+    /// they are listed, then `tasks.d/*.toml` next to it in name order.
+    ///
+    /// A task can only be defined in one file. When files define a notifier
+    /// with the same name, the first one read wins, so the main file can
+    /// override shared notifiers. Webhooks from every file are kept.
+    ///
+    /// A main config file from an older version of crony is upgraded in place
+    /// first. Other files are only upgraded in memory.
     pub fn load() -> Result<Self> {
-        let config_path = get_config_path();
+        let config_path = get_config_path()?;
+        if config_path.exists() && migrate::is_outdated(&config_path)? {
+            let _lock = ConfigLock::acquire()?;
+            migrate::upgrade_file(&config_path)?;
+        }
+        Self::read_all()
+    }
+
+    /// Does the work of [`TaskConfig::load`] without upgrading the main
+    /// file, for callers that already hold the config lock.
+    fn read_all() -> Result<Self> {
+        Self::read_from(&get_config_path()?)
+    }
 
+    /// Reads `config_path` and the files it brings in.
+    fn read_from(config_path: &Path) -> Result<Self> {
         if !config_path.exists() {
             if let Some(parent) = config_path.parent() {
                 fs::create_dir_all(parent)?;
@@ -19,43 +90,480 @@ impl TaskConfig {
             return Ok(TaskConfig::default());
         }
 
-        let content = fs::read_to_string(config_path)?;
-        let config: TaskConfig = toml::from_str(&content)?;
+        let mut config = Self::read(config_path)?;
+        for path in config.extra_files(config_path)? {
+            let extra = Self::read(&path)?;
+            for (key, set) in [
+                ("include", !extra.include.is_empty()),
+                ("defaults", !extra.defaults.is_empty()),
+            ] {
+                if set {
+                    return Err(CronyError::Config(format!(
+                        "{}: {key} is only allowed in {}",
+                        path.display(),
+                        config_path.display()
+                    )));
+                }
+            }
+            config.merge(extra, &path, config_path)?;
+        }
+        config.apply_defaults();
         Ok(config)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let config_path = get_config_path();
+    /// Reads a single config file, without the files it includes.
+    fn read(path: &Path) -> Result<Self> {
+        let mut content = fs::read_to_string(path)
+            .map_err(|e| CronyError::Config(format!("Can't read {}: {e}", path.display())))?;
+        if let Some((_, upgraded)) = migrate::migrate(&content, path)? {
+            content = upgraded;
+        }
+        toml::from_str(&content).map_err(|e| CronyError::Config(format!("{}: {e}", path.display())))
+    }
 
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+    pub fn extra_files(&self, config_path: &Path) -> Result<Vec<PathBuf>> {
+        let dir = config_path.parent().unwrap_or(Path::new("."));
+        let mut files: Vec<PathBuf> = self.include.iter().map(|path| dir.join(path)).collect();
+
+        let drop_in_dir = dir.join("tasks.d");
+        if drop_in_dir.is_dir() {
+            let mut drop_ins: Vec<PathBuf> = fs::read_dir(drop_in_dir)?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.is_file() && path.extension() == Some("toml".as_ref()))
+                .collect();
+            drop_ins.sort();
+            files.extend(drop_ins);
         }
 
-        let content = toml::to_string_pretty(self)?;
-        fs::write(config_path, content)?;
+        let mut seen = HashSet::new();
+        files.retain(|path| seen.insert(path.clone()));
+        Ok(files)
+    }
+
+    pub fn merge(&mut self, other: TaskConfig, path: &Path, config_path: &Path) -> Result<()> {
+        for (name, task) in other.tasks {
+            if self.tasks.contains_key(&name) {
+                let existing = self.sources.get(&name).map_or(config_path, |p| p.as_path());
+                return Err(CronyError::Config(format!(
+                    "Task '{name}' is defined in both {} and {}",
+                    existing.display(),
+                    path.display()
+                )));
+            }
+            self.sources.insert(name.clone(), path.to_path_buf());
+            self.tasks.insert(name, task);
+        }
+        for (name, notifier) in other.notifiers {
+            self.notifiers.entry(name).or_insert(notifier);
+        }
+        self.webhooks.extend(other.webhooks);
         Ok(())
     }
 
-    pub fn add_task(&mut self, name: String, task: Task) -> Result<()> {
-        if self.tasks.contains_key(&name) {
-            return Err(CronyError::Task(format!("Task '{name}' already exists")));
+    /// Gives every task the `[defaults]` to fall back on.
+    pub fn apply_defaults(&mut self) {
+        for task in self.tasks.values_mut() {
+            task.defaults = self.defaults.clone();
         }
-        self.tasks.insert(name, task);
-        self.save()
     }
 
-    pub fn remove_task(&mut self, name: &str) -> Result<Option<Task>> {
-        let task = self.tasks.remove(name);
-        self.save()?;
-        Ok(task)
+    /// The file `task` was read from, or `None` for the main config file.
+    pub fn source(&self, task: &str) -> Option<&Path> {
+        self.sources.get(task).map(PathBuf::as_path)
+    }
+
+    /// Fails if a task that lives in a file other than the main config was
+    /// deleted or changed since `previous`, as only the main file is saved.
+    fn check_sources(&self, previous: &IndexMap<String, Task>) -> Result<()> {
+        for (name, source) in &self.sources {
+            if self.tasks.get(name) != previous.get(name) {
+                return Err(CronyError::Config(format!(
+                    "Task '{name}' is defined in {}, edit that file to change it",
+                    source.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the tasks from the main config file back to it, keeping its
+    /// other settings, with [`write_file`].
+    fn save(&self) -> Result<()> {
+        let config_path = get_config_path()?;
+        let mut own = if config_path.exists() {
+            Self::read(&config_path)?
+        } else {
+            TaskConfig::default()
+        };
+        own.tasks = self
+            .tasks
+            .iter()
+            .filter(|(name, _)| !self.sources.contains_key(*name))
+            .map(|(name, task)| (name.clone(), task.clone()))
+            .collect();
+        own.version = CONFIG_VERSION;
+
+        write_file(&config_path, &toml::to_string_pretty(&own)?)
+    }
+
+    pub fn add_task(&mut self, name: String, task: Task) -> Result<()> {
+        self.modify(|tasks| {
+            if tasks.contains_key(&name) {
+                return Err(CronyError::Task(format!("Task '{name}' already exists")));
+            }
+            tasks.insert(name, task);
+            Ok(())
+        })
+    }
+
+    pub fn remove_task(&mut self, name: &str) -> Result<()> {
+        self.modify(|tasks| {
+            tasks
+                .shift_remove(name)
+                .ok_or_else(|| CronyError::Task(format!("Task '{name}' does not exist")))?;
+            Ok(())
+        })
     }
 
     pub fn update_task(&mut self, name: &str, task: Task) -> Result<()> {
-        if !self.tasks.contains_key(name) {
-            return Err(CronyError::Task(format!("Task '{name}' does not exist")));
+        self.modify(|tasks| {
+            *task_mut(tasks, name)? = task;
+            Ok(())
+        })
+    }
+
+    /// Renames a task in place, updating the tasks that reference it and
+    /// moving its run history along with it.
+    pub fn rename_task(&mut self, old: &str, new: &str) -> Result<()> {
+        // The old definition isn't trashed, since restoring it would bring
+        // back a second copy of the task under its old name.
+        self.apply(Keep::Nothing, |tasks, _| {
+            check_new_name(tasks, new)?;
+            let Some((index, _, mut task)) = tasks.shift_remove_full(old) else {
+                return Err(CronyError::Task(format!("Task '{old}' does not exist")));
+            };
+            task.name = new.to_string();
+            tasks.shift_insert(index, new.to_string(), task);
+
+            for task in tasks.values_mut() {
+                for target in task
+                    .depends_on
+                    .iter_mut()
+                    .chain(&mut task.on_success)
+                    .chain(&mut task.on_failure)
+                {
+                    if target == old {
+                        *target = new.to_string();
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        history::rename(old, new)
+    }
+
+    /// Copies a task under a new name, right after the original. The copy
+    /// starts without any run history.
+    pub fn duplicate_task(&mut self, name: &str, new: &str) -> Result<()> {
+        self.modify(|tasks| {
+            check_new_name(tasks, new)?;
+            let Some((index, _, task)) = tasks.get_full(name) else {
+                return Err(CronyError::Task(format!("Task '{name}' does not exist")));
+            };
+            let task = Task {
+                name: new.to_string(),
+                ..task.clone()
+            };
+            tasks.shift_insert(index + 1, new.to_string(), task);
+            Ok(())
+        })
+    }
+
+    /// Applies `change` to the latest tasks on disk and saves them once,
+    /// leaving the file as it was if `change` fails or the result doesn't
+    /// validate. Deleted and changed tasks, and the names of added ones, are
+    /// kept in the trash so the change can be undone.
+    pub fn modify(
+        &mut self,
+        change: impl FnOnce(&mut IndexMap<String, Task>) -> Result<()>,
+    ) -> Result<()> {
+        self.apply(Keep::Change, |tasks, _| change(tasks))
+    }
+
+    /// Reloads, changes and saves the config while holding its lock, so
+    /// changes made by other crony processes in the meantime aren't lost.
+    /// The trash is only updated once the config has been saved.
+    fn apply<T>(
+        &mut self,
+        keep: Keep,
+        change: impl FnOnce(&mut IndexMap<String, Task>, &mut Trash) -> Result<T>,
+    ) -> Result<T> {
+        let _lock = ConfigLock::acquire()?;
+        let mut trash = Trash::load()?;
+        let mut config = Self::read_all()?;
+        let previous = config.tasks.clone();
+
+        let result = change(&mut config.tasks, &mut trash)?;
+        config.apply_defaults();
+        config.check_sources(&previous)?;
+        config.validate()?;
+        config.save()?;
+
+        if keep != Keep::Nothing {
+            trash.record(&previous, &config.tasks, keep == Keep::Undo);
         }
-        self.tasks.insert(name.to_string(), task);
-        self.save()
+        trash.save()?;
+
+        *self = config;
+        Ok(result)
+    }
+
+    /// Reverts the most recent change: removes the tasks it added and puts
+    /// back the ones it deleted or changed. Whatever this replaces is kept in
+    /// the trash. Returns the change that was undone.
+    pub fn undo(&mut self) -> Result<Batch> {
+        self.apply(Keep::Undo, |tasks, trash| {
+            let batch = trash
+                .take_last_change()
+                .ok_or_else(|| CronyError::Task("Nothing to undo".into()))?;
+            batch.clone().undo_into(tasks);
+            Ok(batch)
+        })
+    }
+
+    /// Puts back the latest deleted or replaced definition of `name`. A task
+    /// it replaces is kept in the trash.
+    pub fn restore(&mut self, name: &str) -> Result<()> {
+        self.apply(Keep::Undo, |tasks, trash| {
+            let trashed = trash.take(name).ok_or_else(|| {
+                CronyError::Task(format!(
+                    "No deleted or changed version of task '{name}' in the trash"
+                ))
+            })?;
+            trashed.restore_into(tasks);
+            Ok(())
+        })
+    }
+
+    /// Moves a task to `position` (starting at 1) in the task order.
+    pub fn move_task(&mut self, name: &str, position: usize) -> Result<()> {
+        // Tasks from other files always come after the main file's tasks, in
+        // the order their files are read.
+        let own = self.tasks.len() - self.sources.len();
+        if let Some(source) = self.source(name) {
+            return Err(CronyError::Config(format!(
+                "Task '{name}' is defined in {}, edit that file to move it",
+                source.display()
+            )));
+        }
+        if position > own {
+            return Err(CronyError::Task(format!(
+                "Position {position} is out of range, expected 1 to {own}"
+            )));
+        }
+        self.apply(Keep::Nothing, |tasks, _| {
+            let Some(from) = tasks.get_index_of(name) else {
+                return Err(CronyError::Task(format!("Task '{name}' does not exist")));
+            };
+            if position == 0 || position > tasks.len() {
+                return Err(CronyError::Task(format!(
+                    "Position {position} is out of range, expected 1 to {}",
+                    tasks.len()
+                )));
+            }
+            tasks.move_index(from, position - 1);
+            Ok(())
+        })
+    }
+
+    /// Looks up a task by name, or by its number in `crony list`.
+    pub fn resolve(&self, reference: &str) -> Result<&str> {
+        if let Some((name, _)) = self.tasks.get_key_value(reference) {
+            return Ok(name);
+        }
+        reference
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| self.tasks.get_index(index.checked_sub(1)?))
+            .map(|(name, _)| name.as_str())
+            .ok_or_else(|| CronyError::Task(format!("No task named or numbered '{reference}'")))
+    }
+
+    /// Fails with the first of [`TaskConfig::problems`], if there are any.
+    pub fn validate(&self) -> Result<()> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem.error),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that each task's `name` matches its key, each task's commands
+    /// and schedule, that every task referenced through `depends_on`,
+    /// `on_success` and `on_failure` exists, that the resulting graph has no
+    /// cycles, and that tasks without a schedule can actually be triggered.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut problem = |task: &str, key, error| {
+            problems.push(Problem {
+                task: task.to_string(),
+                key,
+                error,
+            })
+        };
+
+        for (name, task) in &self.tasks {
+            if task.name != *name {
+                problem(
+                    name,
+                    Some("name"),
+                    CronyError::Task(format!(
+                        "Task '{name}' has name '{}', which must match its key in [tasks.{name}]",
+                        task.name
+                    )),
+                );
+            }
+            if let Err(e) = task.validate() {
+                problem(name, None, e);
+            }
+            if !task.is_triggered_only() {
+                if let Err(e) = task.get_schedule() {
+                    // The cron parser's own message ends with the reason,
+                    // after the schedule and a pointer into it.
+                    let reason = match &e {
+                        CronyError::Schedule(message) => message.lines().last().unwrap_or_default(),
+                        _ => "",
+                    };
+                    let error = CronyError::Schedule(format!(
+                        "Task '{name}' has an invalid schedule '{}': {reason}",
+                        task.schedule
+                    ));
+                    problem(name, Some("schedule"), error);
+                }
+            }
+
+            for (key, targets) in [
+                ("depends_on", &task.depends_on),
+                ("on_success", &task.on_success),
+                ("on_failure", &task.on_failure),
+            ] {
+                for target in targets {
+                    if target == name {
+                        let error = CronyError::Graph(format!("Task '{name}' references itself"));
+                        problem(name, Some(key), error);
+                    } else if !self.tasks.contains_key(target) {
+                        let error = CronyError::Graph(format!(
+                            "Task '{name}' references unknown task '{target}'"
+                        ));
+                        problem(name, Some(key), error);
+                    }
+                }
+            }
+
+            if !task.is_triggered_only() && !task.depends_on.is_empty() {
+                let error = CronyError::Graph(format!(
+                    "Task '{name}' has both a schedule and depends_on, leave out the schedule \
+                     to run it once its dependencies have succeeded"
+                ));
+                problem(name, Some("depends_on"), error);
+            }
+            if task.is_triggered_only() && task.depends_on.is_empty() && !self.is_triggered(name) {
+                let error = CronyError::Graph(format!(
+                    "Task '{name}' has no schedule and is never triggered by another task"
+                ));
+                problem(name, None, error);
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut names: Vec<_> = self.tasks.keys().collect();
+        names.sort();
+        for name in names {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.find_cycle(name, &mut visited, &mut path) {
+                let error =
+                    CronyError::Graph(format!("Dependency cycle detected: {}", cycle.join(" -> ")));
+                problem(cycle[0], None, error);
+            }
+        }
+
+        problems
+    }
+
+    /// Takes the tasks with `problems` out of the config so the others can
+    /// still run, returning why each one was taken out.
+    pub fn disable(&mut self, problems: Vec<Problem>) -> BTreeMap<String, String> {
+        let mut disabled = BTreeMap::new();
+        for problem in problems {
+            self.tasks.shift_remove(&problem.task);
+            let message = problem.error.message();
+            disabled
+                .entry(problem.task)
+                .and_modify(|reason: &mut String| *reason = format!("{reason}; {message}"))
+                .or_insert(message);
+        }
+        disabled
+    }
+
+    /// Tasks that run after `name` completes, either because they depend on
+    /// it or because it triggers them.
+    pub fn downstream(&self, name: &str) -> Vec<&str> {
+        let Some(task) = self.tasks.get(name) else {
+            return Vec::new();
+        };
+
+        let mut downstream: Vec<&str> = self
+            .tasks
+            .iter()
+            .filter(|(_, other)| other.depends_on.iter().any(|d| d == name))
+            .map(|(other, _)| other.as_str())
+            .chain(task.on_success.iter().map(String::as_str))
+            .chain(task.on_failure.iter().map(String::as_str))
+            .collect();
+        downstream.sort_unstable();
+        downstream.dedup();
+        downstream
+    }
+
+    fn is_triggered(&self, name: &str) -> bool {
+        self.tasks.values().any(|task| {
+            task.on_success
+                .iter()
+                .chain(&task.on_failure)
+                .any(|t| t == name)
+        })
+    }
+
+    fn find_cycle<'a>(
+        &'a self,
+        name: &'a str,
+        visited: &mut HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<&'a str>> {
+        if let Some(start) = path.iter().position(|n| *n == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return Some(cycle);
+        }
+        if !visited.insert(name) {
+            return None;
+        }
+
+        path.push(name);
+        for next in self.downstream(name) {
+            if let Some(cycle) = self.find_cycle(next, visited, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    /// Resolves each of `references` with [`TaskConfig::resolve`].
+    pub fn resolve_all(&self, references: &[String]) -> Result<Vec<String>> {
+        references
+            .iter()
+            .map(|reference| self.resolve(reference).map(str::to_string))
+            .collect()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -67,10 +575,419 @@ impl TaskConfig {
     }
 }
 
-fn get_config_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap_or_else(|_| ".".into());
-    PathBuf::from(home)
-        .join(".config")
-        .join("crony")
-        .join("tasks.toml")
+/// The task called `name`, for changes made through [`TaskConfig::modify`].
+pub fn task_mut<'a>(tasks: &'a mut IndexMap<String, Task>, name: &str) -> Result<&'a mut Task> {
+    tasks
+        .get_mut(name)
+        .ok_or_else(|| CronyError::Task(format!("Task '{name}' does not exist")))
+}
+
+fn check_new_name(tasks: &IndexMap<String, Task>, name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(CronyError::Task("Task name cannot be empty".into()));
+    }
+    if tasks.contains_key(name) {
+        return Err(CronyError::Task(format!("Task '{name}' already exists")));
+    }
+    Ok(())
+}
+
+/// How long to wait for another crony process to release the config.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What [`TaskConfig::apply`] keeps in the trash.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Keep {
+    Nothing,
+    /// What the change removed, replaced or added.
+    Change,
+    /// What an undo or restore replaced, which later undos skip.
+    Undo,
+}
+
+/// An advisory lock on the config file, held while it is reloaded, changed
+/// and saved. It is released when dropped.
+struct ConfigLock {
+    _file: fs::File,
+}
+
+impl ConfigLock {
+    fn acquire() -> Result<Self> {
+        Self::acquire_for(&get_config_path()?, LOCK_TIMEOUT)
+    }
+
+    /// Locks `config_path`, waiting up to `timeout` for another process to
+    /// release it.
+    fn acquire_for(config_path: &Path, timeout: Duration) -> Result<Self> {
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(sibling_path(config_path, "lock"))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(CronyError::Locked(config_path.to_path_buf()))
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Writes `content` to a temporary file and renames it over `path`, so the
+/// file is never left half-written.
+pub fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp_path = sibling_path(path, "tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+/// `path` with `.<extension>` appended, e.g. `tasks.toml.lock`.
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{extension}"));
+    path.with_file_name(name)
+}
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses `path` as the config file instead of the default, for `--config`.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+/// The config file in use: the `--config` path, then `CRONY_CONFIG`, then
+/// `crony/tasks.toml` in the user's config directory.
+pub fn get_config_path() -> Result<PathBuf> {
+    if let Some(path) = CONFIG_PATH.get() {
+        return Ok(path.clone());
+    }
+    if let Some(path) = env_path("CRONY_CONFIG") {
+        return Ok(path);
+    }
+    base_dir("XDG_CONFIG_HOME", "APPDATA", ".config")
+        .map(|dir| dir.join("crony").join("tasks.toml"))
+        .ok_or_else(|| {
+            CronyError::Cli(
+                "Can't find the config directory, set HOME or use --config to choose a file".into(),
+            )
+        })
+}
+
+/// Directory for runtime state such as the scheduler's live state.
+pub fn get_state_dir() -> Result<PathBuf> {
+    base_dir("XDG_STATE_HOME", "LOCALAPPDATA", ".local/state")
+        .map(|dir| dir.join("crony"))
+        .ok_or_else(|| {
+            CronyError::Cli("Can't find the state directory, set HOME or XDG_STATE_HOME".into())
+        })
+}
+
+/// Directory for the state of the config file in use, such as its trash and
+/// run history. That's the state directory itself for the default config
+/// file, and `configs/<file stem>-<hash of its path>` inside it for others.
+pub fn get_config_state_dir() -> Result<PathBuf> {
+    let state_dir = get_state_dir()?;
+    let config_path = std::path::absolute(get_config_path()?)?;
+    let default = base_dir("XDG_CONFIG_HOME", "APPDATA", ".config")
+        .map(|dir| dir.join("crony").join("tasks.toml"));
+    if default.is_some_and(|default| config_path == default) {
+        return Ok(state_dir);
+    }
+
+    // FNV-1a, so the directory stays the same across builds.
+    let hash = config_path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let stem = config_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    Ok(state_dir
+        .join("configs")
+        .join(format!("{stem}-{hash:016x}")))
+}
+
+/// Where systemd looks for the current user's own units.
+pub fn get_systemd_user_dir() -> Result<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", "APPDATA", ".config")
+        .map(|dir| dir.join("systemd").join("user"))
+        .ok_or_else(|| {
+            CronyError::Cli("Can't find the systemd user directory, set HOME or use --dir".into())
+        })
+}
+
+/// `$xdg_var` (or `%windows_var%` on Windows), falling back to `home_dir`
+/// inside the home directory.
+fn base_dir(xdg_var: &str, windows_var: &str, home_dir: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env_path(windows_var);
+    }
+
+    // The XDG spec says relative paths in these variables are invalid.
+    env_path(xdg_var)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(env_path("HOME")?.join(home_dir)))
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> TaskConfig {
+        let mut config: TaskConfig = toml::from_str(toml).unwrap();
+        config.apply_defaults();
+        config
+    }
+
+    fn messages(config: &TaskConfig) -> Vec<String> {
+        config
+            .problems()
+            .iter()
+            .map(|problem| problem.error.message())
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_pipeline() {
+        let config = config(
+            r#"
+            [tasks.backup]
+            name = "backup"
+            schedule = "0 0 2 * * *"
+            command = "./backup.sh"
+            on_failure = ["alert"]
+
+            [tasks.compress]
+            name = "compress"
+            command = "./compress.sh"
+            depends_on = ["backup"]
+
+            [tasks.alert]
+            name = "alert"
+            command = "./alert.sh"
+            "#,
+        );
+        assert!(messages(&config).is_empty(), "{:?}", messages(&config));
+    }
+
+    #[test]
+    fn finds_every_cycle_once() {
+        let config = config(
+            r#"
+            [tasks.a]
+            name = "a"
+            schedule = "0 0 * * * *"
+            command = "true"
+            on_success = ["b"]
+
+            [tasks.b]
+            name = "b"
+            command = "true"
+            on_success = ["c"]
+
+            [tasks.c]
+            name = "c"
+            command = "true"
+            on_failure = ["b"]
+
+            [tasks.d]
+            name = "d"
+            command = "true"
+            on_success = ["e"]
+
+            [tasks.e]
+            name = "e"
+            command = "true"
+            depends_on = ["d"]
+            on_success = ["d"]
+            "#,
+        );
+        assert_eq!(
+            messages(&config),
+            [
+                "Dependency cycle detected: b -> c -> b",
+                "Dependency cycle detected: d -> e -> d",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_bad_references() {
+        let config = config(
+            r#"
+            [tasks.a]
+            name = "a"
+            schedule = "0 0 * * * *"
+            command = "true"
+            on_success = ["a", "missing"]
+
+            [tasks.b]
+            name = "b"
+            schedule = "0 0 * * * *"
+            command = "true"
+            depends_on = ["a"]
+            "#,
+        );
+        let problems = config.problems();
+        let keys: Vec<_> = problems
+            .iter()
+            .map(|problem| (problem.task.as_str(), problem.key))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("a", Some("on_success")),
+                ("a", Some("on_success")),
+                ("b", Some("depends_on")),
+                ("a", None),
+            ]
+        );
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crony-config-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn lock_excludes_others_until_dropped() {
+        let dir = temp_dir("lock");
+        let path = dir.join("tasks.toml");
+        let lock = ConfigLock::acquire_for(&path, Duration::ZERO).unwrap();
+        assert!(matches!(
+            ConfigLock::acquire_for(&path, Duration::from_millis(100)),
+            Err(CronyError::Locked(locked)) if locked == path
+        ));
+        drop(lock);
+        ConfigLock::acquire_for(&path, Duration::ZERO).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn write_file_replaces_the_file_without_leftovers() {
+        let dir = temp_dir("write");
+        let path = dir.join("tasks.toml");
+        write_file(&path, "old").unwrap();
+        write_file(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["tasks.toml"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// A `[tasks.<name>]` table running `true` every hour.
+    fn task_table(name: &str) -> String {
+        format!(
+            "[tasks.{name}]\nname = \"{name}\"\nschedule = \"0 0 * * * *\"\ncommand = \"true\"\n\n"
+        )
+    }
+
+    #[test]
+    fn merges_includes_and_drop_ins_in_order() {
+        let dir = temp_dir("merge");
+        fs::create_dir_all(dir.join("tasks.d")).unwrap();
+        let main = dir.join("tasks.toml");
+        fs::write(
+            &main,
+            format!(
+                "include = [\"shared.toml\"]\n\n{}[notifiers.ops]\ntype = \"desktop\"\n",
+                task_table("main")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("shared.toml"),
+            format!(
+                "{}[notifiers.ops]\ntype = \"command\"\ncommand = \"true\"\n\n\
+                 [[webhooks]]\nurl = \"http://localhost/a\"\n",
+                task_table("shared")
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("tasks.d/b.toml"), task_table("second")).unwrap();
+        fs::write(
+            dir.join("tasks.d/a.toml"),
+            format!(
+                "{}[[webhooks]]\nurl = \"http://localhost/b\"\n",
+                task_table("first")
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("tasks.d/notes.txt"), "not a config file").unwrap();
+
+        let config = TaskConfig::read_from(&main).unwrap();
+        let names: Vec<_> = config.tasks.keys().map(String::as_str).collect();
+        assert_eq!(names, ["main", "shared", "first", "second"]);
+        assert_eq!(config.source("main"), None);
+        assert_eq!(
+            config.source("first"),
+            Some(dir.join("tasks.d/a.toml").as_path())
+        );
+        // The main file's notifier wins, webhooks from every file are kept.
+        assert!(matches!(config.notifiers["ops"], Notifier::Desktop {}));
+        let urls: Vec<_> = config.webhooks.iter().map(|w| w.url.as_str()).collect();
+        assert_eq!(urls, ["http://localhost/a", "http://localhost/b"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_task_defined_twice() {
+        let dir = temp_dir("duplicate");
+        fs::create_dir_all(dir.join("tasks.d")).unwrap();
+        let main = dir.join("tasks.toml");
+        fs::write(&main, task_table("backup")).unwrap();
+        fs::write(dir.join("tasks.d/backup.toml"), task_table("backup")).unwrap();
+
+        let error = TaskConfig::read_from(&main).err().unwrap().message();
+        assert!(
+            error.contains("Task 'backup' is defined in both"),
+            "{error}"
+        );
+        assert!(error.contains("tasks.d/backup.toml"), "{error}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_main_file_may_include() {
+        let dir = temp_dir("nested-include");
+        let main = dir.join("tasks.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&main, "include = [\"more.toml\"]\n").unwrap();
+        fs::write(dir.join("more.toml"), "include = [\"other.toml\"]\n").unwrap();
+
+        let error = TaskConfig::read_from(&main).err().unwrap().message();
+        assert!(error.contains("include is only allowed in"), "{error}");
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
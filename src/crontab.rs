@@ -0,0 +1,427 @@
+use crate::task::{parse_schedule, Task, TaskOutcome};
+use indexmap::IndexMap;
+use regex::Regex;
+use std::{collections::BTreeSet, path::Path, sync::OnceLock};
+
+/// Cron day names, from Sunday.
+pub const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// What `crony import crontab` made of a crontab file.
+#[derive(Debug, Default)]
+pub struct Import {
+    /// Tasks in file order. Their names come from the comment above the line
+    /// or from the command, and may clash with each other or existing tasks.
+    pub tasks: Vec<Task>,
+    /// Line numbers of jobs that couldn't be translated, and why.
+    pub skipped: Vec<(usize, String)>,
+    /// Line numbers of jobs that were imported but behave a little
+    /// differently under crony, and how.
+    pub notes: Vec<(usize, String)>,
+}
+
+/// Translates the jobs in a crontab. `system` is for `/etc/crontab` and
+/// `/etc/cron.d` files, which have a user field before the command.
+/// `local_timezone` is the system's time zone, which cron reads schedules in
+/// unless `CRON_TZ` says otherwise.
+pub fn parse(content: &str, system: bool, local_timezone: Option<&str>) -> Import {
+    let mut import = Import::default();
+    let mut env = IndexMap::new();
+    let mut shell = None;
+    let mut timezone = local_timezone.map(str::to_string);
+    let mut mailto: Option<String> = None;
+    let mut comment: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            comment = None;
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim());
+            continue;
+        }
+        if let Some((key, value)) = env_assignment(line) {
+            match key {
+                "MAILTO" => {
+                    if !value.is_empty() {
+                        import.notes.push((
+                            number,
+                            format!(
+                                "cron mails {value} any output of the jobs below, crony only \
+                                 notifies on failures, through the configured notifiers"
+                            ),
+                        ));
+                    }
+                    mailto = Some(value);
+                }
+                "SHELL" => shell = Some(format!("{value} -c")),
+                "CRON_TZ" => timezone = Some(value),
+                _ => {
+                    env.insert(key.to_string(), value);
+                }
+            }
+            continue;
+        }
+
+        let label = comment.take();
+        let (schedule, command) = match job(line, system) {
+            Ok((schedule, user, command)) => {
+                if let Some(user) = user {
+                    import.notes.push((
+                        number,
+                        format!("runs as {user} in the crontab, crony runs it as its own user"),
+                    ));
+                }
+                (schedule, command)
+            }
+            Err(reason) => {
+                import.skipped.push((number, reason));
+                continue;
+            }
+        };
+
+        if timezone.is_none() {
+            import.notes.push((
+                number,
+                "runs in the system time zone under cron, which couldn't be found, so crony \
+                 reads its schedule in UTC"
+                    .into(),
+            ));
+        }
+
+        let notify_on = mailto
+            .as_deref()
+            .filter(|address| !address.is_empty())
+            .map(|_| vec![TaskOutcome::Failure, TaskOutcome::Timeout]);
+
+        import.tasks.push(Task {
+            name: task_name(label, &command),
+            schedule,
+            command,
+            shell: shell.clone(),
+            env: env.clone(),
+            timezone: timezone.clone(),
+            notify_on,
+            ..Default::default()
+        });
+    }
+
+    import
+}
+
+/// A `NAME=value` line, with the value's quotes removed.
+fn env_assignment(line: &str) -> Option<(&str, String)> {
+    static ASSIGNMENT: OnceLock<Regex> = OnceLock::new();
+    let assignment =
+        ASSIGNMENT.get_or_init(|| Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*=\s*(.*)$").unwrap());
+    let captures = assignment.captures(line)?;
+    let value = captures.get(2)?.as_str().trim();
+    let value = ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value);
+    Some((captures.get(1)?.as_str(), value.to_string()))
+}
+
+/// Splits a job line into its crony schedule, its user for system crontabs
+/// and its command.
+fn job(line: &str, system: bool) -> Result<(String, Option<String>, String), String> {
+    let (schedule, rest) = if line.starts_with('@') {
+        let (name, rest) = first_word(line);
+        (macro_schedule(name)?, rest)
+    } else {
+        let mut rest = line;
+        let mut fields = [""; 5];
+        for field in &mut fields {
+            (*field, rest) = first_word(rest);
+        }
+        (schedule(fields)?, rest)
+    };
+
+    let (user, rest) = if system {
+        let (user, rest) = first_word(rest);
+        (Some(user.to_string()).filter(|user| !user.is_empty()), rest)
+    } else {
+        (None, rest)
+    };
+
+    let command = rest.trim();
+    if command.is_empty() {
+        return Err("the line has no command".into());
+    }
+    if command.replace("\\%", "").contains('%') {
+        return Err("the command uses % to send input to it, which crony can't do".into());
+    }
+    Ok((schedule, user, command.replace("\\%", "%")))
+}
+
+fn first_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    text.split_once(char::is_whitespace).unwrap_or((text, ""))
+}
+
+fn macro_schedule(name: &str) -> Result<String, String> {
+    Ok(match name {
+        "@yearly" | "@annually" => "0 0 0 1 1 *",
+        "@monthly" => "0 0 0 1 * *",
+        "@weekly" => "0 0 0 * * SUN",
+        "@daily" | "@midnight" => "0 0 0 * * *",
+        "@hourly" => "0 0 * * * *",
+        "@reboot" => return Err("@reboot runs at boot, which crony has no schedule for".into()),
+        _ => return Err(format!("{name} isn't a schedule cron knows")),
+    }
+    .to_string())
+}
+
+/// Turns the five crontab time fields into a crony schedule, which has
+/// seconds first.
+fn schedule(fields: [&str; 5]) -> Result<String, String> {
+    let [minute, hour, day_of_month, month, day_of_week] = fields;
+    if day_of_week.is_empty() {
+        return Err("the line has fewer than five time fields".into());
+    }
+    if day_of_month != "*" && day_of_week != "*" {
+        return Err(
+            "it sets both day of month and day of week, which cron matches when either \
+             matches but crony only when both do"
+                .into(),
+        );
+    }
+
+    let day_of_week = days_of_week(day_of_week)?;
+    let schedule = format!("0 {minute} {hour} {day_of_month} {month} {day_of_week}");
+    match parse_schedule(&schedule) {
+        Ok(_) => Ok(schedule),
+        Err(e) => Err(format!(
+            "the schedule isn't valid: {}",
+            e.message()
+                .lines()
+                .rfind(|line| line.contains(char::is_alphanumeric))
+                .unwrap_or_default()
+                .trim()
+        )),
+    }
+}
+
+/// Rewrites a crontab day of week field with day names, since crontab
+/// counts Sunday as 0 or 7 and crony counts it as 1.
+fn days_of_week(field: &str) -> Result<String, String> {
+    if field == "*" {
+        return Ok(field.to_string());
+    }
+
+    let day = |value: &str| -> Result<usize, String> {
+        let number = match value.parse::<usize>() {
+            Ok(number) => number,
+            Err(_) => DAY_NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(value))
+                .ok_or_else(|| format!("'{value}' isn't a day of the week"))?,
+        };
+        match number {
+            0..=7 => Ok(number),
+            _ => Err(format!("'{value}' isn't a day of the week")),
+        }
+    };
+
+    let mut days = BTreeSet::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<usize>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("'{step}' isn't a valid step"))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (0, 6),
+            Some((start, end)) => match (day(start)?, day(end)?) {
+                (start, end) if start > end => {
+                    return Err(format!(
+                        "the day of week range '{range}' counts down, cron ranges go from \
+                         low to high"
+                    ));
+                }
+                range => range,
+            },
+            None if step > 1 => (day(range)?, 6),
+            None => (day(range)?, day(range)?),
+        };
+        days.extend((start..=end).step_by(step).map(|day| (day % 7) as u32));
+    }
+    Ok(compress(&days, "-", |day| DAY_NAMES[day as usize].into()))
+}
+
+/// Lists `values`, writing runs of three or more as ranges.
+pub fn compress(values: &BTreeSet<u32>, range: &str, show: impl Fn(u32) -> String) -> String {
+    let values: Vec<u32> = values.iter().copied().collect();
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1] == values[j] + 1 {
+            j += 1;
+        }
+        if j - i >= 2 {
+            parts.push(format!("{}{range}{}", show(values[i]), show(values[j])));
+        } else {
+            parts.extend(values[i..=j].iter().map(|value| show(*value)));
+        }
+        i = j + 1;
+    }
+    parts.join(",")
+}
+
+/// A task name from the comment above a job, or else from the program it
+/// runs.
+fn task_name(comment: Option<&str>, command: &str) -> String {
+    let program = first_word(command).0;
+    let program = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(program);
+    let name = slug(comment.unwrap_or(program));
+    if name.is_empty() {
+        "job".into()
+    } else {
+        name
+    }
+}
+
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(40);
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compresses_runs_into_ranges() {
+        let show = |value: u32| value.to_string();
+        let set = |values: &[u32]| values.iter().copied().collect::<BTreeSet<_>>();
+        assert_eq!(compress(&set(&[1, 2, 3, 5]), "-", show), "1-3,5");
+        assert_eq!(compress(&set(&[1, 2, 4, 5, 6]), "..", show), "1,2,4..6");
+        assert_eq!(compress(&set(&[]), "-", show), "");
+    }
+
+    #[test]
+    fn rewrites_days_of_week_with_names() {
+        assert_eq!(days_of_week("*").unwrap(), "*");
+        assert_eq!(days_of_week("1-5").unwrap(), "MON-FRI");
+        assert_eq!(days_of_week("0,6").unwrap(), "SUN,SAT");
+        assert_eq!(days_of_week("7").unwrap(), "SUN");
+        assert_eq!(days_of_week("*/2").unwrap(), "SUN,TUE,THU,SAT");
+        assert_eq!(days_of_week("mon-wed,fri").unwrap(), "MON-WED,FRI");
+    }
+
+    #[test]
+    fn rejects_bad_days_of_week() {
+        assert!(days_of_week("8").is_err());
+        assert!(days_of_week("funday").is_err());
+        assert!(days_of_week("1/0").is_err());
+        assert!(days_of_week("5-1").unwrap_err().contains("counts down"));
+    }
+
+    #[test]
+    fn imports_jobs_with_their_settings() {
+        let import = parse(
+            "SHELL=/bin/bash\n\
+             PATH=\"/usr/bin:/bin\"\n\
+             MAILTO=ops@example.com\n\
+             # Nightly backup\n\
+             30 2 * * * /usr/local/bin/backup.sh --full\n\
+             MAILTO=\"\"\n\
+             @hourly date +\\%H\n",
+            false,
+            Some("Europe/Berlin"),
+        );
+        assert!(import.skipped.is_empty());
+
+        let [backup, date] = import.tasks.as_slice() else {
+            panic!("expected two tasks, got {:?}", import.tasks);
+        };
+        assert_eq!(backup.name, "nightly-backup");
+        assert_eq!(backup.schedule, "0 30 2 * * *");
+        assert_eq!(backup.command, "/usr/local/bin/backup.sh --full");
+        assert_eq!(backup.shell.as_deref(), Some("/bin/bash -c"));
+        assert_eq!(backup.env["PATH"], "/usr/bin:/bin");
+        assert_eq!(backup.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(
+            backup.notify_on,
+            Some(vec![TaskOutcome::Failure, TaskOutcome::Timeout])
+        );
+
+        assert_eq!(date.name, "date");
+        assert_eq!(date.schedule, "0 0 * * * *");
+        assert_eq!(date.command, "date +%H");
+        assert_eq!(date.notify_on, None);
+    }
+
+    #[test]
+    fn cron_tz_overrides_the_system_time_zone() {
+        let import = parse(
+            "CRON_TZ=Asia/Tokyo\n0 9 * * 1-5 ./standup\n",
+            false,
+            Some("UTC"),
+        );
+        assert_eq!(import.tasks[0].timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(import.tasks[0].schedule, "0 0 9 * * MON-FRI");
+        assert_eq!(import.tasks[0].name, "standup");
+    }
+
+    #[test]
+    fn notes_an_unknown_system_time_zone() {
+        let import = parse("0 9 * * * ./standup\n", false, None);
+        assert_eq!(import.tasks[0].timezone, None);
+        assert_eq!(import.notes.len(), 1);
+        assert!(import.notes[0].1.contains("UTC"));
+    }
+
+    #[test]
+    fn skips_lines_crony_cannot_reproduce() {
+        let import = parse(
+            "@reboot ./start\n\
+             0 0 1 * 1 ./both\n\
+             0 12 * * * cat %input\n\
+             0 12 * * *\n\
+             0 12 * *\n\
+             0 25 * * * ./late\n",
+            false,
+            Some("UTC"),
+        );
+        assert!(import.tasks.is_empty());
+        let lines: Vec<usize> = import.skipped.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 2, 3, 4, 5, 6]);
+        assert!(import.skipped[5]
+            .1
+            .starts_with("the schedule isn't valid: "));
+        assert!(import.skipped[5].1.len() > "the schedule isn't valid: ".len());
+    }
+
+    #[test]
+    fn system_crontabs_have_a_user_field() {
+        let import = parse(
+            "0 4 * * * root run-parts /etc/cron.daily\n",
+            true,
+            Some("UTC"),
+        );
+        assert_eq!(import.tasks[0].command, "run-parts /etc/cron.daily");
+        assert_eq!(import.tasks[0].name, "run-parts");
+        assert!(import.notes[0].1.contains("runs as root"));
+    }
+}
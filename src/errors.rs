@@ -8,6 +8,12 @@ pub enum CronyError {
     #[error("Schedule parsing error: {0}")]
     Schedule(String),
 
+    #[error("Task graph error: {0}")]
+    Graph(String),
+
+    #[error("Config error: {0}")]
+    Config(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -17,8 +23,35 @@ pub enum CronyError {
     #[error("TOML serialization error: {0}")]
     TomlSer(#[from] toml::ser::Error),
 
+    #[error("Notification error: {0}")]
+    Notify(String),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{} is being changed by another crony process, try again", .0.display())]
+    Locked(std::path::PathBuf),
+
     #[error("CLI interaction error: {0}")]
     Cli(String),
 }
 
+impl CronyError {
+    /// The error's message, without the prefix saying what kind it is.
+    pub fn message(&self) -> String {
+        match self {
+            CronyError::Task(message)
+            | CronyError::Schedule(message)
+            | CronyError::Graph(message)
+            | CronyError::Config(message) => message.clone(),
+            error => error.to_string(),
+        }
+    }
+
+    /// Whether the user cancelled a prompt, e.g. by pressing Esc.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, CronyError::Io(e) if e.kind() == std::io::ErrorKind::Interrupted)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CronyError>;
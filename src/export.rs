@@ -0,0 +1,513 @@
+use crate::crontab::{compress, DAY_NAMES};
+use crate::errors::{CronyError, Result};
+use crate::task::{normalize_schedule, read_env_file, Task, DEFAULT_SHELL};
+use std::{collections::BTreeSet, fmt::Write, time::Duration};
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const SYSTEMD_DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Tasks rendered for another scheduler, and what about them couldn't be
+/// carried over exactly.
+#[derive(Debug, Default)]
+pub struct Export {
+    /// File names and contents. A crontab export is a single file.
+    pub files: Vec<(String, String)>,
+    pub warnings: Vec<String>,
+}
+
+/// A task's schedule split into its cron fields.
+struct Fields {
+    second: String,
+    minute: String,
+    hour: String,
+    day_of_month: String,
+    month: String,
+    /// Numbered 1 to 7 from Sunday, as crony reads it.
+    day_of_week: String,
+    year: Option<String>,
+}
+
+impl Fields {
+    fn of(task: &Task) -> Result<Self> {
+        let cron = normalize_schedule(&task.schedule)?;
+        let cron = match cron.as_str() {
+            "@yearly" | "@annually" => "0 0 0 1 1 *",
+            "@monthly" => "0 0 0 1 * *",
+            "@weekly" => "0 0 0 * * 1",
+            "@daily" => "0 0 0 * * *",
+            "@hourly" => "0 0 * * * *",
+            cron => cron,
+        };
+        let fields: Vec<String> = cron
+            .split_whitespace()
+            .map(|field| match field {
+                "?" => "*".to_string(),
+                field => field.to_string(),
+            })
+            .collect();
+        match fields.as_slice() {
+            [second, minute, hour, day_of_month, month, day_of_week, rest @ ..] => Ok(Self {
+                second: second.clone(),
+                minute: minute.clone(),
+                hour: hour.clone(),
+                day_of_month: day_of_month.clone(),
+                month: month.clone(),
+                day_of_week: day_of_week.clone(),
+                year: rest.first().cloned(),
+            }),
+            _ => Err(CronyError::Schedule(format!(
+                "Task '{}' has a schedule crony can't export: {cron}",
+                task.name
+            ))),
+        }
+    }
+}
+
+/// Renders `tasks` as the lines of a crontab. Tasks should have their
+/// defaults applied, see [`Task::effective`].
+pub fn crontab(tasks: &[Task]) -> Result<Export> {
+    let mut export = Export::default();
+    let mut content = String::from("# Exported from crony\n");
+    let mut timezone = None;
+
+    for task in tasks {
+        let name = &task.name;
+        let mut warn = |message: String| export.warnings.push(format!("Task '{name}' {message}"));
+        if task.is_triggered_only() {
+            warn("only runs when triggered, which crontab can't do, so it is left out".into());
+            continue;
+        }
+
+        let fields = Fields::of(task)?;
+        if fields.second != "0" {
+            warn(format!(
+                "runs at second {}, crontab runs it at the start of the minute",
+                fields.second
+            ));
+        }
+        if fields.year.as_deref().is_some_and(|year| year != "*") {
+            warn("is limited to some years, which crontab has no field for".into());
+        }
+        if fields.day_of_month != "*" && fields.day_of_week != "*" {
+            warn(
+                "sets both day of month and day of week, crontab runs it when either \
+                 matches rather than both"
+                    .into(),
+            );
+        }
+        let month = if fields.month.chars().any(|c| c.is_ascii_alphabetic()) {
+            let months = expand(&fields.month, 1, 12, &MONTH_NAMES)?;
+            compress(&months, "-", |month| month.to_string())
+        } else {
+            fields.month
+        };
+        let day_of_week = if fields.day_of_week == "*" {
+            fields.day_of_week
+        } else {
+            let days = expand(&fields.day_of_week, 1, 7, &DAY_NAMES)?;
+            compress(&days, "-", |day| (day - 1).to_string())
+        };
+
+        if task.env_file.is_some() {
+            warn("loads an env_file, which isn't exported".into());
+        }
+        if task.get_steps().iter().any(|step| step.timeout.is_some()) {
+            warn("has a timeout, which crontab has no equivalent of".into());
+        }
+        if task.retry.is_some() {
+            warn("retries failed runs, which crontab can't do".into());
+        }
+        if !task.on_success.is_empty() || !task.on_failure.is_empty() {
+            warn("triggers other tasks, which crontab can't do".into());
+        }
+        warn_unexported(task, &mut warn);
+
+        let tz = task.get_timezone()?;
+        if timezone != Some(tz) {
+            writeln!(content, "CRON_TZ={}", tz.name()).unwrap();
+            timezone = Some(tz);
+        }
+        let paused = if task.paused {
+            warn("is paused, so its line is commented out".into());
+            "# "
+        } else {
+            ""
+        };
+        writeln!(
+            content,
+            "# {name}\n{paused}{} {} {} {month} {day_of_week} {}",
+            fields.minute,
+            fields.hour,
+            fields.day_of_month,
+            crontab_command(task).replace('%', "\\%")
+        )
+        .unwrap();
+    }
+
+    if timezone.is_some() {
+        export.warnings.push(
+            "CRON_TZ is only understood by some crons, such as cronie, others read \
+             schedules in the system time zone"
+                .into(),
+        );
+    }
+    export.files.push(("crontab".into(), content));
+    Ok(export)
+}
+
+/// The task as a single shell command line. Steps run in subshells, as each
+/// would run in its own shell under crony.
+fn crontab_command(task: &Task) -> String {
+    let steps = task.get_steps();
+    let script = match steps.as_slice() {
+        [step] => step.command.clone(),
+        steps => steps
+            .iter()
+            .map(|step| match step.continue_on_error {
+                true => format!("({} || true)", step.command),
+                false => format!("({})", step.command),
+            })
+            .collect::<Vec<_>>()
+            .join(" && "),
+    };
+
+    let shell = task.shell.as_deref().unwrap_or(DEFAULT_SHELL);
+    let mut line = if shell == DEFAULT_SHELL && task.env.is_empty() {
+        script
+    } else {
+        let mut line = String::new();
+        for (key, value) in &task.env {
+            write!(line, "{key}={} ", shell_quote(value)).unwrap();
+        }
+        write!(line, "{shell} {}", shell_quote(&script)).unwrap();
+        line
+    };
+    if let Some(cwd) = &task.cwd {
+        line = format!("cd {} && {line}", shell_quote(&cwd.to_string_lossy()));
+    }
+    line
+}
+
+/// Renders `tasks` as systemd units: a `.service` for each task and a
+/// `.timer` for each scheduled one. Tasks should have their defaults
+/// applied, see [`Task::effective`].
+pub fn systemd(tasks: &[Task]) -> Result<Export> {
+    let mut export = Export::default();
+
+    for task in tasks {
+        let name = &task.name;
+        let mut warn = |message: String| export.warnings.push(format!("Task '{name}' {message}"));
+
+        let mut service = format!("[Unit]\nDescription=crony task {name}\n");
+        for (key, triggered) in [
+            ("OnSuccess", &task.on_success),
+            ("OnFailure", &task.on_failure),
+        ] {
+            for next in triggered {
+                if !tasks.iter().any(|task| task.name == *next) {
+                    warn(format!("triggers '{next}', which isn't being exported"));
+                }
+                writeln!(service, "{key}={}", unit_name(next, "service")).unwrap();
+            }
+        }
+
+        service.push_str("\n[Service]\nType=oneshot\n");
+        if let Some(cwd) = &task.cwd {
+            writeln!(
+                service,
+                "WorkingDirectory={}",
+                unit_escape(&cwd.to_string_lossy())
+            )
+            .unwrap();
+        }
+        for (key, value) in &task.env {
+            let assignment = format!("{key}={value}");
+            writeln!(service, "Environment={}", unit_quote(&assignment, false)).unwrap();
+        }
+        if let Some(env_file) = &task.env_file {
+            let overridden = read_env_file(env_file)
+                .unwrap_or_default()
+                .iter()
+                .any(|(key, _)| task.env.contains_key(key));
+            if overridden {
+                warn(
+                    "sets variables in both env and env_file, systemd lets the env_file win".into(),
+                );
+            }
+            writeln!(
+                service,
+                "EnvironmentFile={}",
+                unit_escape(&env_file.to_string_lossy())
+            )
+            .unwrap();
+        }
+
+        let shell = task.shell.as_deref().unwrap_or(DEFAULT_SHELL);
+        let steps = task.get_steps();
+        let mut timeout = Some(Duration::ZERO);
+        for step in &steps {
+            let ignore_failure = if step.continue_on_error { "-" } else { "" };
+            let arguments: Vec<String> = shell
+                .split_whitespace()
+                .chain([step.command.as_str()])
+                .map(|word| unit_quote(word, true))
+                .collect();
+            writeln!(service, "ExecStart={ignore_failure}{}", arguments.join(" ")).unwrap();
+            timeout = match (timeout, step.get_timeout()?) {
+                (Some(total), Some(step)) => Some(total + step),
+                _ => None,
+            };
+        }
+        let timed = steps.iter().filter(|step| step.timeout.is_some()).count();
+        match timeout {
+            Some(timeout) if timed > 0 => {
+                if steps.len() > 1 {
+                    warn("has step timeouts, which are added up into one for the whole run".into());
+                }
+                writeln!(service, "TimeoutStartSec={}s", timeout.as_secs().max(1)).unwrap();
+            }
+            None if timed > 0 => {
+                warn("has timeouts on only some steps, so the run has none".into());
+            }
+            _ => {}
+        }
+
+        if task.retry.is_some() {
+            warn("retries failed runs, which isn't exported".into());
+        }
+        if !task.depends_on.is_empty() {
+            warn("waits for other tasks to succeed, which isn't exported".into());
+        }
+        warn_unexported(task, &mut warn);
+        if task.paused && !task.is_triggered_only() {
+            warn("is paused, so leave its timer disabled".into());
+        }
+
+        export.files.push((unit_name(name, "service"), service));
+        if !task.is_triggered_only() {
+            let timer = format!(
+                "[Unit]\nDescription=Schedule of crony task {name}\n\n\
+                 [Timer]\nOnCalendar={}\nAccuracySec=1s\n\n\
+                 [Install]\nWantedBy=timers.target\n",
+                on_calendar(task)?
+            );
+            export.files.push((unit_name(name, "timer"), timer));
+        }
+    }
+
+    Ok(export)
+}
+
+/// The task's schedule as a systemd calendar event, e.g.
+/// `Mon..Fri *-*-* 09:00:00 UTC`.
+fn on_calendar(task: &Task) -> Result<String> {
+    let fields = Fields::of(task)?;
+    let days = match fields.day_of_week.as_str() {
+        "*" => String::new(),
+        field => {
+            // systemd weeks start on Monday.
+            let days: BTreeSet<u32> = expand(field, 1, 7, &DAY_NAMES)?
+                .into_iter()
+                .map(|day| (day + 5) % 7)
+                .collect();
+            match days.len() {
+                7 => String::new(),
+                _ => compress(&days, "..", |day| SYSTEMD_DAY_NAMES[day as usize].into()) + " ",
+            }
+        }
+    };
+    let year = fields.year.as_deref().unwrap_or("*");
+    Ok(format!(
+        "{days}{}-{}-{} {}:{}:{} {}",
+        calendar_field(year, 1970, 2099, &[])?,
+        calendar_field(&fields.month, 1, 12, &MONTH_NAMES)?,
+        calendar_field(&fields.day_of_month, 1, 31, &[])?,
+        calendar_field(&fields.hour, 0, 23, &[])?,
+        calendar_field(&fields.minute, 0, 59, &[])?,
+        calendar_field(&fields.second, 0, 59, &[])?,
+        task.get_timezone()?.name()
+    ))
+}
+
+/// A cron field in systemd's calendar syntax, which writes ranges as `a..b`
+/// and has no ranges with steps.
+fn calendar_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<String> {
+    if field == "*" {
+        return Ok(field.into());
+    }
+    if let Some(step) = field.strip_prefix("*/") {
+        return Ok(format!("{min}/{step}"));
+    }
+    let plain = field.split(',').all(|part| {
+        part.split_once('/')
+            .map_or(part, |(start, _)| start)
+            .parse::<u32>()
+            .is_ok()
+    });
+    if plain {
+        return Ok(field.into());
+    }
+    let values = expand(field, min, max, names)?;
+    Ok(compress(&values, "..", |value| value.to_string()))
+}
+
+/// The values a cron field matches. `names` name the values from `min` up.
+fn expand(field: &str, min: u32, max: u32, names: &[&str]) -> Result<BTreeSet<u32>> {
+    let invalid = || CronyError::Schedule(format!("Can't read the cron field '{field}'"));
+    let value = |text: &str| -> Result<u32> {
+        let value = match text.parse::<u32>() {
+            Ok(value) => value,
+            Err(_) => names
+                .iter()
+                .zip(min..)
+                .find(|(name, _)| text.len() >= 3 && text[..3].eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+                .ok_or_else(invalid)?,
+        };
+        match (min..=max).contains(&value) {
+            true => Ok(value),
+            false => Err(invalid()),
+        }
+    };
+
+    let mut values = BTreeSet::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            None if part.contains('/') => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        values.extend((start..=end).step_by(step.max(1)));
+    }
+    Ok(values)
+}
+
+fn warn_unexported(task: &Task, warn: &mut impl FnMut(String)) {
+    if task.notify_on.as_ref().is_some_and(|on| !on.is_empty()) {
+        warn("sends notifications, which aren't exported".into());
+    }
+    if task.healthcheck.is_some() {
+        warn("pings a healthcheck, which isn't exported".into());
+    }
+}
+
+/// The unit file name for a task, escaping characters systemd doesn't allow
+/// in unit names the way `systemd-escape` does.
+pub fn unit_name(task: &str, kind: &str) -> String {
+    let mut name = String::from("crony-");
+    for c in task.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | ':' | '_' | '.' | '-' => name.push(c),
+            c => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    write!(name, "\\x{byte:02x}").unwrap();
+                }
+            }
+        }
+    }
+    format!("{name}.{kind}")
+}
+
+/// Escapes `%`, which systemd expands in most unit settings.
+fn unit_escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Quotes a word of a unit setting such as `ExecStart=`. `$` is doubled
+/// where systemd would expand it as a variable.
+pub fn unit_quote(word: &str, expands_variables: bool) -> String {
+    let plain =
+        !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "\"'\\$%".contains(c));
+    if plain {
+        return word.to_string();
+    }
+    let mut escaped = unit_escape(word).replace('\\', "\\\\").replace('"', "\\\"");
+    if expands_variables {
+        escaped = escaped.replace('$', "$$");
+    }
+    format!("\"{escaped}\"")
+}
+
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(schedule: &str) -> Task {
+        Task::new("job".into(), schedule.into(), "echo hi".into())
+    }
+
+    #[test]
+    fn expands_cron_fields() {
+        let values = |field, min, max, names: &[&str]| {
+            expand(field, min, max, names)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values("*", 1, 7, &DAY_NAMES), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(values("MON-FRI", 1, 7, &DAY_NAMES), [2, 3, 4, 5, 6]);
+        assert_eq!(values("1-23/6", 0, 23, &[]), [1, 7, 13, 19]);
+        assert_eq!(values("50/5", 0, 59, &[]), [50, 55]);
+        assert_eq!(values("jan,Mar", 1, 12, &MONTH_NAMES), [1, 3]);
+        assert!(expand("0", 1, 7, &DAY_NAMES).is_err());
+        assert!(expand("someday", 1, 7, &DAY_NAMES).is_err());
+    }
+
+    #[test]
+    fn converts_schedules_to_calendar_events() {
+        let calendar = |schedule| on_calendar(&task(schedule)).unwrap();
+        assert_eq!(calendar("0 30 2 * * *"), "*-*-* 2:30:0 UTC");
+        assert_eq!(
+            calendar("*/20 */15 9-17 * * MON-FRI"),
+            "Mon..Fri *-*-* 9..17:0/15:0/20 UTC"
+        );
+        assert_eq!(calendar("0 0 8 * * 1,7"), "Sat,Sun *-*-* 8:0:0 UTC");
+        assert_eq!(
+            calendar("0 0 1-23/8 1,15 JAN-MAR *"),
+            "*-1..3-1,15 1,9,17:0:0 UTC"
+        );
+
+        let mut berlin = task("0 0 9 * * *");
+        berlin.timezone = Some("Europe/Berlin".into());
+        assert_eq!(on_calendar(&berlin).unwrap(), "*-*-* 9:0:0 Europe/Berlin");
+    }
+
+    #[test]
+    fn renders_crontab_lines() {
+        let mut weekend = task("0 0 8 * * 1,7");
+        weekend.name = "weekend".into();
+        weekend.command = "date +%F".into();
+        let mut seconds = task("30 * * * * *");
+        seconds.name = "seconds".into();
+
+        let export = crontab(&[weekend, seconds]).unwrap();
+        let content = &export.files[0].1;
+        assert!(content.contains("CRON_TZ=UTC\n"));
+        assert!(content.contains("# weekend\n0 8 * * 0,6 date +\\%F\n"));
+        assert!(content.contains("# seconds\n* * * * * echo hi\n"));
+        assert!(export
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("Task 'seconds' runs at second 30")));
+    }
+
+    #[test]
+    fn quotes_unit_words() {
+        assert_eq!(unit_quote("sh", true), "sh");
+        assert_eq!(unit_quote("echo $HOME 100%", true), "\"echo $$HOME 100%%\"");
+        assert_eq!(unit_quote("A=$B", false), "\"A=$B\"");
+        assert_eq!(unit_name("a b", "timer"), "crony-a\\x20b.timer");
+    }
+}
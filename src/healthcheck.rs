@@ -0,0 +1,124 @@
+use crate::errors::{CronyError, Result};
+use crate::history::RunRecord;
+use crate::task::TaskOutcome;
+use crate::webhook::agent;
+use std::{thread, time::Duration};
+
+const ATTEMPTS: u32 = 3;
+
+/// A healthchecks.io-style ping around a task run.
+#[derive(Clone, Debug)]
+pub enum Ping {
+    Start,
+    Finish(RunRecord),
+}
+
+impl Ping {
+    /// The URL to ping for a check at `base`, and the request body to send.
+    fn request(&self, base: &str) -> (String, String) {
+        let base = base.trim_end_matches('/');
+        match self {
+            Ping::Start => (format!("{base}/start"), String::new()),
+            Ping::Finish(record) if record.outcome == TaskOutcome::Success => {
+                (base.to_string(), record.output_tail().to_string())
+            }
+            Ping::Finish(record) => {
+                let exit_code = record
+                    .exit_code()
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| record.outcome.to_string());
+                (
+                    format!("{base}/fail"),
+                    format!("exit code: {exit_code}\n\n{}", record.output_tail()),
+                )
+            }
+        }
+    }
+
+    /// Pings the check at `base`, retrying a few times. Blocks until the ping
+    /// is delivered or all attempts fail.
+    pub fn send(&self, base: &str) -> Result<()> {
+        let (url, body) = self.request(base);
+        let mut attempt = 1;
+        loop {
+            let result = agent()
+                .post(&url)
+                .header("Content-Type", "text/plain")
+                .send(&body);
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt >= ATTEMPTS => {
+                    return Err(CronyError::Notify(format!("Ping to '{url}' failed: {e}")));
+                }
+                Err(_) => {
+                    thread::sleep(Duration::from_secs(1));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::StepRecord;
+    use crate::webhook::tests::stand_in;
+    use chrono::Local;
+
+    fn finished(outcome: TaskOutcome, exit_code: Option<i32>) -> Ping {
+        let now = Local::now();
+        Ping::Finish(RunRecord {
+            run_id: "run-1".into(),
+            task: "backup".into(),
+            scheduled_at: None,
+            started_at: now,
+            finished_at: now,
+            outcome,
+            steps: vec![StepRecord {
+                command: "./backup.sh".into(),
+                outcome,
+                exit_code,
+                duration_ms: 0,
+                error: None,
+                output_tail: "copied 3 files\n".into(),
+            }],
+            retries: 0,
+        })
+    }
+
+    #[test]
+    fn start_pings_the_start_url() {
+        let (url, body) = Ping::Start.request("https://hc-ping.com/abc/");
+        assert_eq!(url, "https://hc-ping.com/abc/start");
+        assert_eq!(body, "");
+    }
+
+    #[test]
+    fn success_pings_the_base_url_with_the_output() {
+        let (url, body) =
+            finished(TaskOutcome::Success, Some(0)).request("https://hc-ping.com/abc");
+        assert_eq!(url, "https://hc-ping.com/abc");
+        assert_eq!(body, "copied 3 files\n");
+    }
+
+    #[test]
+    fn failure_pings_the_fail_url_with_the_exit_code() {
+        let base = "https://hc-ping.com/abc";
+        let (url, body) = finished(TaskOutcome::Failure, Some(2)).request(base);
+        assert_eq!(url, "https://hc-ping.com/abc/fail");
+        assert_eq!(body, "exit code: 2\n\ncopied 3 files\n");
+
+        let (url, body) = finished(TaskOutcome::Timeout, None).request(base);
+        assert_eq!(url, "https://hc-ping.com/abc/fail");
+        assert!(body.starts_with("exit code: timeout\n\n"));
+    }
+
+    #[test]
+    fn send_posts_to_the_check() {
+        let (base, server) = stand_in(&[200]);
+        Ping::Start.send(&base).unwrap();
+        let (head, _) = server.join().unwrap().remove(0);
+        assert!(head.starts_with("POST /notify/start "));
+    }
+}
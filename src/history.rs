@@ -0,0 +1,229 @@
+use crate::config::{get_config_state_dir, write_file};
+use crate::errors::Result;
+use crate::task::TaskOutcome;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// A single execution of a task, appended to the task's history file once the
+/// run completes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub task: String,
+    /// When the run was due, if it was started by the schedule rather than
+    /// triggered by another task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<DateTime<Local>>,
+    pub started_at: DateTime<Local>,
+    pub finished_at: DateTime<Local>,
+    pub outcome: TaskOutcome,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepRecord>,
+    /// How many times the run was retried before this outcome.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub command: String,
+    pub outcome: TaskOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The last few kilobytes of the step's combined stdout and stderr.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output_tail: String,
+}
+
+impl RunRecord {
+    pub fn duration_ms(&self) -> u64 {
+        (self.finished_at - self.started_at)
+            .num_milliseconds()
+            .max(0) as u64
+    }
+
+    /// Exit code of the last step that ran, if it exited normally.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.steps.last().and_then(|step| step.exit_code)
+    }
+
+    /// Output tail of the last step that ran.
+    pub fn output_tail(&self) -> &str {
+        self.steps
+            .last()
+            .map(|step| step.output_tail.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Once a history file grows past this many bytes, its oldest runs are
+/// dropped until it is down to half of it.
+const HISTORY_MAX_BYTES: u64 = 2 * 1024 * 1024;
+
+/// How much of a history file is read at a time when looking for the latest
+/// runs.
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+pub fn new_run_id(started_at: &DateTime<Local>) -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let seq = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{seq:04}", started_at.format("%Y%m%dT%H%M%S%3f"))
+}
+
+pub fn append(record: &RunRecord) -> Result<()> {
+    let path = history_path(&record.task)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    if file.metadata()?.len() > HISTORY_MAX_BYTES {
+        trim(&path)?;
+    }
+    Ok(())
+}
+
+/// Drops the oldest runs from a history file that has grown too big.
+fn trim(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let keep = HISTORY_MAX_BYTES as usize / 2;
+    let mut start = content.len().saturating_sub(keep);
+    if start > 0 {
+        // Keep whole lines only.
+        start = content[start..]
+            .find('\n')
+            .map_or(content.len(), |i| start + i + 1);
+    }
+    write_file(path, &content[start..])
+}
+
+/// The most recent run of `task`, if it has run before.
+pub fn last(task: &str) -> Result<Option<RunRecord>> {
+    Ok(recent(task, 1)?.pop())
+}
+
+/// Up to `limit` of the latest runs of `task`, newest first.
+pub fn recent(task: &str, limit: usize) -> Result<Vec<RunRecord>> {
+    let path = history_path(task)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = read_tail(&path, limit)?;
+    Ok(content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect())
+}
+
+/// Moves the run history of `old` over to `new` after a rename.
+pub fn rename(old: &str, new: &str) -> Result<()> {
+    let path = history_path(old)?;
+    if path.exists() {
+        fs::rename(path, history_path(new)?)?;
+    }
+    Ok(())
+}
+
+/// The end of the file at `path`, holding at least its last `lines` whole
+/// lines, or all of it if it is shorter. The first line may be cut off.
+fn read_tail(path: &Path, lines: usize) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut end = file.metadata()?.len();
+    let mut buffer = Vec::new();
+    while end > 0 && buffer.iter().filter(|&&b| b == b'\n').count() <= lines {
+        let start = end.saturating_sub(TAIL_CHUNK);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+        end = start;
+    }
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+fn history_path(task: &str) -> Result<PathBuf> {
+    Ok(get_config_state_dir()?
+        .join("history")
+        .join(format!("{}.jsonl", file_stem(task))))
+}
+
+/// `task` made safe to use as a file name: anything but letters, digits,
+/// `-`, `_` and inner dots is percent-encoded, so names like `a/../x` stay
+/// inside the history directory.
+fn file_stem(task: &str) -> String {
+    let mut stem = String::with_capacity(task.len());
+    for (i, byte) in task.bytes().enumerate() {
+        match byte {
+            b'.' if i > 0 => stem.push('.'),
+            b'-' | b'_' => stem.push(byte as char),
+            _ if byte.is_ascii_alphanumeric() => stem.push(byte as char),
+            _ => stem.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    stem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn file_stems_stay_in_the_history_dir() {
+        assert_eq!(file_stem("backup.daily"), "backup.daily");
+        assert_eq!(file_stem("a/../../x"), "a%2F..%2F..%2Fx");
+        assert_eq!(file_stem(".."), "%2E.");
+        assert_eq!(file_stem("50% off"), "50%25%20off");
+    }
+
+    #[test]
+    fn reads_whole_lines_from_the_end() {
+        let path = env::temp_dir().join(format!("crony-history-tail-{}", std::process::id()));
+        let content: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        fs::write(&path, &content).unwrap();
+
+        let tail = read_tail(&path, 3).unwrap();
+        assert!(tail.len() < content.len());
+        let last: Vec<_> = tail.lines().rev().take(3).collect();
+        assert_eq!(last, ["line 19999", "line 19998", "line 19997"]);
+
+        fs::write(&path, "only\n").unwrap();
+        assert_eq!(read_tail(&path, 3).unwrap(), "only\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn trimming_keeps_the_newest_whole_lines() {
+        let path = env::temp_dir().join(format!("crony-history-trim-{}", std::process::id()));
+        let line = format!("{}\n", "x".repeat(99));
+        let count = HISTORY_MAX_BYTES as usize / line.len() + 10;
+        fs::write(&path, line.repeat(count)).unwrap();
+
+        trim(&path).unwrap();
+        let trimmed = fs::read_to_string(&path).unwrap();
+        assert!(trimmed.len() <= HISTORY_MAX_BYTES as usize / 2);
+        assert!(trimmed.lines().all(|l| l.len() == 99));
+        fs::remove_file(path).unwrap();
+    }
+}
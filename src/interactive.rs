@@ -1,35 +1,37 @@
 use crate::{
-    config::TaskConfig,
+    config::{task_mut, TaskConfig},
     errors::{CronyError, Result},
-    task::{parse_schedule, Task},
+    task::{parse_schedule, Step, Task, TaskOutcome},
     Commands,
 };
-use cliclack::{input, log::info, select};
+use cliclack::{
+    confirm, input,
+    log::{self, info},
+    multiselect, note, select,
+};
 use console::style;
+use std::fmt::Display;
 
-pub fn handle_interactive_mode(mode: Option<Commands>, config: &mut TaskConfig) -> Result<()> {
+pub fn handle_interactive_mode(mode: Commands, config: &mut TaskConfig) -> Result<()> {
     match mode {
-        Some(Commands::Create) => handle_create(config),
-        Some(Commands::List) => handle_list(config),
-        Some(Commands::Delete) => handle_delete(config),
-        Some(Commands::Edit) => handle_edit(config),
-        Some(Commands::Run) => handle_run(config),
+        Commands::Create => handle_create(config),
+        Commands::List(_) => handle_list(config),
+        Commands::Delete(_) => handle_delete(config),
+        Commands::Edit => handle_edit(config),
+        Commands::Run(_) => handle_run(config),
+        Commands::Pause(_) => handle_pause(config, true),
+        Commands::Resume(_) => handle_pause(config, false),
+        Commands::Trigger(_) => handle_trigger(config),
+        Commands::Tag(_) => handle_tag(config),
+        Commands::Rename(_) => handle_rename(config),
+        Commands::Duplicate(_) => handle_duplicate(config),
+        Commands::Undo => handle_undo(config),
         _ => panic!("invalid mode selected"),
     }
 }
 
 fn handle_create(config: &mut TaskConfig) -> Result<()> {
-    let existing_task_names: Vec<String> = config.tasks.keys().cloned().collect();
-
-    let name: String = input("enter a name for your task")
-        .validate(move |input: &String| match input.trim() {
-            "" => Err("task name cannot be empty".into()),
-            name if existing_task_names.contains(&name.to_string()) => {
-                Err(format!("task name '{name}' already exists."))
-            }
-            _ => Ok(()),
-        })
-        .interact()?;
+    let name = input_new_name("enter a name for your task", config)?;
 
     let raw_cron: String = input("input a cron expression for the task")
         .placeholder("0 * * * * *")
@@ -55,7 +57,7 @@ fn handle_create(config: &mut TaskConfig) -> Result<()> {
         "task '{}' created with schedule '{}' and command '{}'",
         style(&task.name).bold().green(),
         style(&task.schedule).bold().yellow(),
-        style(&task.command).bold().blue()
+        style(task.command_summary()).bold().blue()
     ))?;
 
     Ok(())
@@ -75,7 +77,7 @@ fn handle_list(config: &TaskConfig) -> Result<()> {
                     style(i + 1).bold().cyan(),
                     style(name).bold().green(),
                     style(&task.schedule).bold().yellow(),
-                    style(&task.command).bold().blue()
+                    style(task.command_summary()).bold().blue()
                 ));
             });
     }
@@ -88,26 +90,144 @@ fn handle_delete(config: &mut TaskConfig) -> Result<()> {
         return Ok(());
     }
 
-    let task_names: Vec<_> = config.tasks.keys().cloned().collect();
-    let task_name_refs: Vec<_> = task_names.iter().map(|s| s.as_str()).collect();
+    let names = select_tasks("select tasks to delete", config, |_| true)?;
+    if !confirm_changes("delete", &names)? {
+        return Ok(());
+    }
+
+    config.modify(|tasks| {
+        for name in &names {
+            tasks.shift_remove(name);
+        }
+        Ok(())
+    })?;
+    info(format!("deleted {}", style(names.join(", ")).bold().red()))?;
+
+    Ok(())
+}
+
+fn handle_pause(config: &mut TaskConfig, paused: bool) -> Result<()> {
+    let (verb, done) = if paused {
+        ("pause", "paused")
+    } else {
+        ("resume", "resumed")
+    };
+    if !config.tasks.values().any(|task| task.paused != paused) {
+        info(format!("no tasks to {verb}"))?;
+        return Ok(());
+    }
+
+    let names = select_tasks(format!("select tasks to {verb}"), config, |task| {
+        task.paused != paused
+    })?;
+    if !confirm_changes(verb, &names)? {
+        return Ok(());
+    }
+
+    config.modify(|tasks| {
+        for name in &names {
+            task_mut(tasks, name)?.paused = paused;
+        }
+        Ok(())
+    })?;
+    info(format!(
+        "{done} {}",
+        style(names.join(", ")).bold().yellow()
+    ))?;
+
+    Ok(())
+}
+
+fn handle_trigger(config: &TaskConfig) -> Result<()> {
+    if config.tasks.values().all(|task| task.paused) {
+        info("no tasks to run")?;
+        return Ok(());
+    }
+
+    let names = select_tasks("select tasks to run now", config, |task| !task.paused)?;
+    if !confirm_changes("run", &names)? {
+        return Ok(());
+    }
+
+    let tasks = names
+        .iter()
+        .map(|name| config.tasks[name.as_str()].clone())
+        .collect();
+    for (name, result) in crate::worker::trigger(tasks)? {
+        match result {
+            Ok(record) if record.outcome == TaskOutcome::Success => info(format!(
+                "task '{}' finished: {}",
+                style(&name).bold().green(),
+                record.outcome
+            ))?,
+            Ok(record) => log::error(format!(
+                "task '{}' finished: {}",
+                style(&name).bold().red(),
+                record.outcome
+            ))?,
+            Err(e) => log::error(format!("task '{name}' could not be recorded: {e}"))?,
+        }
+    }
 
-    let selected_task = select("select a task to delete")
-        .items(
-            &task_name_refs
-                .iter()
-                .map(|name| (Some(*name), *name, ""))
-                .collect::<Vec<_>>(),
-        )
+    Ok(())
+}
+
+fn handle_tag(config: &mut TaskConfig) -> Result<()> {
+    if config.is_empty() {
+        info("no tasks to tag")?;
+        return Ok(());
+    }
+
+    let remove = select("add or remove a tag?")
+        .item(false, "add", "")
+        .item(true, "remove", "")
+        .interact()?;
+    let tag: String = input("tag")
+        .validate(|input: &String| match input.trim() {
+            "" => Err("tag cannot be empty"),
+            _ => Ok(()),
+        })
         .interact()?;
+    let tag = tag.trim().to_string();
 
-    if let Some(task_name) = selected_task {
-        config.remove_task(task_name)?;
-        info(format!(
-            "task '{}' deleted successfully!",
-            style(task_name).bold().red()
-        ))?;
+    let names = if remove {
+        if !config.tasks.values().any(|task| task.tags.contains(&tag)) {
+            info(format!("no tasks are tagged '{tag}'"))?;
+            return Ok(());
+        }
+        select_tasks(format!("select tasks to untag '{tag}'"), config, |task| {
+            task.tags.contains(&tag)
+        })?
+    } else {
+        select_tasks(format!("select tasks to tag '{tag}'"), config, |task| {
+            !task.tags.contains(&tag)
+        })?
+    };
+    let verb = if remove {
+        format!("remove tag '{tag}' from")
+    } else {
+        format!("add tag '{tag}' to")
+    };
+    if !confirm_changes(&verb, &names)? {
+        return Ok(());
     }
 
+    config.modify(|tasks| {
+        for name in &names {
+            let tags = &mut task_mut(tasks, name)?.tags;
+            if remove {
+                tags.retain(|t| *t != tag);
+            } else if !tags.contains(&tag) {
+                tags.push(tag.clone());
+            }
+        }
+        Ok(())
+    })?;
+    info(format!(
+        "updated tags on {}",
+        style(names.join(", ")).bold().green()
+    ))?;
+
     Ok(())
 }
 
@@ -117,74 +237,219 @@ fn handle_edit(config: &mut TaskConfig) -> Result<()> {
         return Ok(());
     }
 
-    let task_names: Vec<String> = config.tasks.keys().cloned().collect();
-    let task_name_refs: Vec<&str> = task_names.iter().map(|s| s.as_str()).collect();
-
-    let selected_task = select("select a task to edit")
-        .items(
-            &task_name_refs
-                .iter()
-                .map(|name| (Some(*name), *name, ""))
-                .collect::<Vec<_>>(),
-        )
-        .interact()?;
+    let selected_task = select_task("select a task to edit", config)?;
 
     if let Some(task_name) = selected_task {
-        let (current_schedule, current_command) = {
-            let task = config.tasks.get(task_name).unwrap();
-            (task.schedule.clone(), task.command.clone())
-        };
+        let current = config.tasks[&task_name].clone();
 
         let new_schedule: String = input("input a new cron expression for the task")
-            .default_input(&current_schedule)
-            .validate(|input: &String| {
-                parse_schedule(input)
+            .default_input(&current.schedule)
+            .required(!current.is_triggered_only())
+            .validate(|input: &String| match input.trim() {
+                "" => Ok(()),
+                input => parse_schedule(input)
                     .map(|_| ())
-                    .map_err(|_| "invalid cron expression")
+                    .map_err(|_| "invalid cron expression"),
             })
             .interact()?;
 
-        let new_command: String = input("input a new command to run")
-            .default_input(&current_command)
-            .validate(|input: &String| match input.trim() {
-                "" => Err("command cannot be empty"),
-                _ => Ok(()),
-            })
-            .interact()?;
+        let mut updated_task = Task {
+            schedule: new_schedule,
+            ..current.clone()
+        };
+
+        if current.steps.is_empty() {
+            updated_task.command = input("input a new command to run")
+                .default_input(&current.command)
+                .validate(|input: &String| match input.trim() {
+                    "" => Err("command cannot be empty"),
+                    _ => Ok(()),
+                })
+                .interact()?;
+        } else {
+            updated_task.steps = edit_steps(&current.steps)?;
+        }
 
-        let updated_task = Task::new(task_name.into(), new_schedule, new_command);
-        config.update_task(task_name, updated_task.clone())?;
+        config.update_task(&task_name, updated_task.clone())?;
 
         info(format!(
             "task '{}' updated with schedule '{}' and command '{}'",
             style(&updated_task.name).bold().green(),
             style(&updated_task.schedule).bold().yellow(),
-            style(&updated_task.command).bold().blue()
+            style(updated_task.command_summary()).bold().blue()
         ))?;
     }
 
     Ok(())
 }
 
+fn handle_undo(config: &mut TaskConfig) -> Result<()> {
+    let batch = config.undo()?;
+    info(format!(
+        "undid the last change: {}",
+        style(batch.summary()).bold().green()
+    ))?;
+    Ok(())
+}
+
+fn handle_rename(config: &mut TaskConfig) -> Result<()> {
+    if config.is_empty() {
+        info("no tasks to rename")?;
+        return Ok(());
+    }
+
+    let Some(task_name) = select_task("select a task to rename", config)? else {
+        return Ok(());
+    };
+    let new_name = input_new_name("enter a new name for the task", config)?;
+    config.rename_task(&task_name, &new_name)?;
+
+    info(format!(
+        "task '{}' renamed to '{}'",
+        style(&task_name).bold().yellow(),
+        style(&new_name).bold().green()
+    ))?;
+    Ok(())
+}
+
+fn handle_duplicate(config: &mut TaskConfig) -> Result<()> {
+    if config.is_empty() {
+        info("no tasks to duplicate")?;
+        return Ok(());
+    }
+
+    let Some(task_name) = select_task("select a task to duplicate", config)? else {
+        return Ok(());
+    };
+    let new_name = input_new_name("enter a name for the copy", config)?;
+    config.duplicate_task(&task_name, &new_name)?;
+
+    info(format!(
+        "task '{}' duplicated as '{}'",
+        style(&task_name).bold().yellow(),
+        style(&new_name).bold().green()
+    ))?;
+    Ok(())
+}
+
+/// Prompts for a task name that isn't taken yet.
+fn input_new_name(prompt: &str, config: &TaskConfig) -> Result<String> {
+    let existing_task_names: Vec<String> = config.tasks.keys().cloned().collect();
+
+    let name: String = input(prompt)
+        .validate(move |input: &String| match input.trim() {
+            "" => Err("task name cannot be empty".into()),
+            name if existing_task_names.contains(&name.to_string()) => {
+                Err(format!("task name '{name}' already exists."))
+            }
+            _ => Ok(()),
+        })
+        .interact()?;
+    Ok(name.trim().to_string())
+}
+
+/// Prompts for each step's command in turn, dropping steps left empty, then
+/// offers to append new ones.
+fn edit_steps(steps: &[Step]) -> Result<Vec<Step>> {
+    let mut edited = Vec::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        let command: String = input(format!("step {} command (empty to remove)", i + 1))
+            .default_input(&step.command)
+            .required(false)
+            .interact()?;
+
+        if !command.trim().is_empty() {
+            edited.push(Step {
+                command,
+                ..step.clone()
+            });
+        }
+    }
+
+    while edited.is_empty()
+        || confirm("add another step?")
+            .initial_value(false)
+            .interact()?
+    {
+        let command: String = input(format!("step {} command", edited.len() + 1))
+            .validate(|input: &String| match input.trim() {
+                "" => Err("command cannot be empty"),
+                _ => Ok(()),
+            })
+            .interact()?;
+        edited.push(Step::new(command));
+    }
+
+    Ok(edited)
+}
+
+/// Prompts for a task, numbered as in `crony list`, with type-to-filter
+/// search over the names.
+fn select_task(prompt: &str, config: &TaskConfig) -> Result<Option<String>> {
+    let items: Vec<_> = config
+        .tasks
+        .keys()
+        .enumerate()
+        .map(|(i, name)| (Some(name.clone()), format!("{}. {name}", i + 1), ""))
+        .collect();
+
+    Ok(select(prompt).items(&items).filter_mode().interact()?)
+}
+
+/// Prompts for any number of the tasks matching `filter`, with type-to-filter
+/// search over the names.
+fn select_tasks(
+    prompt: impl Display,
+    config: &TaskConfig,
+    filter: impl Fn(&Task) -> bool,
+) -> Result<Vec<String>> {
+    let items: Vec<_> = config
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, task))| filter(task))
+        .map(|(i, (name, _))| (name.clone(), format!("{}. {name}", i + 1), ""))
+        .collect();
+
+    Ok(multiselect(prompt).items(&items).filter_mode().interact()?)
+}
+
+/// Shows what is about to change and asks before going ahead. Deleting
+/// defaults to no, anything else to yes.
+fn confirm_changes(verb: &str, names: &[String]) -> Result<bool> {
+    note(
+        format!("{verb} {} task(s)", names.len()),
+        names
+            .iter()
+            .map(|name| format!("• {name}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )?;
+    Ok(confirm(format!("{verb} these tasks?"))
+        .initial_value(verb != "delete")
+        .interact()?)
+}
+
 fn handle_run(config: &TaskConfig) -> Result<()> {
     if config.is_empty() {
         info("no tasks to run")?;
         return Ok(());
     }
 
+    config.validate()?;
+
     info(format!(
         "starting {} task(s) in background...",
         style(config.len()).bold().cyan()
     ))?;
 
-    tracing_subscriber::fmt::init();
-
     let rt = tokio::runtime::Runtime::new()
         .map_err(|e| CronyError::Task(format!("Failed to create runtime: {}", e)))?;
 
-    let tasks = config.tasks.clone();
+    let config = config.clone();
     rt.block_on(async {
-        crate::worker::run_all_tasks(tasks).await;
+        crate::worker::run_all_tasks(config, None, Default::default()).await;
     });
 
     Ok(())
@@ -0,0 +1,404 @@
+use crate::config::{get_config_path, TaskConfig};
+use crate::errors::Result;
+use crate::migrate::CONFIG_VERSION;
+use crate::task::read_env_file;
+use console::style;
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Error,
+    /// Doesn't stop crony from running, but is probably a mistake. Treated as
+    /// an error by `crony validate --strict`.
+    Warning,
+}
+
+/// A problem found in a config file.
+#[derive(Debug)]
+pub struct Issue {
+    pub path: PathBuf,
+    /// Line and column, both starting at 1.
+    pub position: Option<(usize, usize)>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{line}:{column}")?;
+        }
+        let severity = match self.severity {
+            Severity::Error => style("error").red().bold(),
+            Severity::Warning => style("warning").yellow().bold(),
+        };
+        write!(f, ": {severity}: {}", self.message)
+    }
+}
+
+/// A config file's text, kept to point issues at the lines they come from.
+struct Source {
+    path: PathBuf,
+    text: String,
+    document: Option<ImDocument<String>>,
+}
+
+impl Source {
+    /// Where the value at `keys` is defined, falling back to the closest
+    /// parent table that exists.
+    fn position(&self, keys: &[&str]) -> Option<(usize, usize)> {
+        let document = self.document.as_ref()?;
+        let span = table_span(document.as_table(), keys)?;
+        Some(line_column(&self.text, span.start))
+    }
+
+    fn issue(&self, keys: &[&str], severity: Severity, message: String) -> Issue {
+        Issue {
+            path: self.path.clone(),
+            position: self.position(keys),
+            severity,
+            message,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Linter {
+    sources: Vec<Source>,
+    issues: Vec<Issue>,
+}
+
+impl Linter {
+    /// Parses a config file, noting syntax errors, values of the wrong type
+    /// and unknown keys. Only the `main` file is warned about an old
+    /// `version`, since the others are only ever upgraded in memory.
+    fn read(&mut self, path: &Path, main: bool) -> Option<TaskConfig> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.issues.push(Issue {
+                    path: path.to_path_buf(),
+                    position: None,
+                    severity: Severity::Error,
+                    message: format!("Can't read file: {e}"),
+                });
+                return None;
+            }
+        };
+
+        let mut unknown = Vec::new();
+        let parsed: std::result::Result<TaskConfig, _> =
+            serde_ignored::deserialize(toml::Deserializer::new(&text), |key| {
+                unknown.push(key.to_string())
+            });
+        let source = Source {
+            path: path.to_path_buf(),
+            document: ImDocument::parse(text.clone()).ok(),
+            text,
+        };
+
+        let config = match parsed {
+            Ok(config) if config.version > CONFIG_VERSION => {
+                let message = format!(
+                    "Config version {} is newer than this crony understands ({CONFIG_VERSION})",
+                    config.version
+                );
+                self.issues
+                    .push(source.issue(&["version"], Severity::Error, message));
+                None
+            }
+            Ok(config) => {
+                if main && config.version < CONFIG_VERSION {
+                    let message = format!(
+                        "Config version {} is out of date, crony reads it as version {CONFIG_VERSION}",
+                        config.version
+                    );
+                    self.issues
+                        .push(source.issue(&["version"], Severity::Warning, message));
+                }
+                for key in unknown {
+                    let keys: Vec<_> = key.split('.').filter(|key| *key != "?").collect();
+                    let message = format!("Unknown key '{}'", keys.join("."));
+                    let issue = source.issue(&keys, Severity::Warning, message);
+                    self.issues.push(issue);
+                }
+                Some(config)
+            }
+            Err(e) => {
+                self.issues.push(Issue {
+                    path: source.path.clone(),
+                    position: e.span().map(|span| line_column(&source.text, span.start)),
+                    severity: Severity::Error,
+                    message: e.message().trim().replace('\n', ", "),
+                });
+                None
+            }
+        };
+        self.sources.push(source);
+        config
+    }
+
+    fn source(&self, path: &Path) -> &Source {
+        self.sources
+            .iter()
+            .find(|source| source.path == path)
+            .expect("every merged file was read")
+    }
+
+    fn push(&mut self, path: &Path, keys: &[&str], severity: Severity, message: String) {
+        let issue = self.source(path).issue(keys, severity, message);
+        self.issues.push(issue);
+    }
+}
+
+/// Checks the config file and every file it pulls in, returning all the
+/// issues found in file and line order.
+pub fn lint() -> Result<Vec<Issue>> {
+    lint_from(&get_config_path()?)
+}
+
+fn lint_from(config_path: &Path) -> Result<Vec<Issue>> {
+    if !config_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut linter = Linter::default();
+    let Some(mut config) = linter.read(config_path, true) else {
+        return Ok(linter.issues);
+    };
+
+    for path in config.extra_files(config_path)? {
+        let Some(mut extra) = linter.read(&path, false) else {
+            continue;
+        };
+        for (key, set) in [
+            ("include", !extra.include.is_empty()),
+            ("defaults", !extra.defaults.is_empty()),
+        ] {
+            if set {
+                let message = format!("{key} is only allowed in {}", config_path.display());
+                linter.push(&path, &[key], Severity::Error, message);
+            }
+        }
+
+        let duplicates: Vec<String> = extra
+            .tasks
+            .keys()
+            .filter(|name| config.tasks.contains_key(*name))
+            .cloned()
+            .collect();
+        for name in duplicates {
+            let first = config.source(&name).unwrap_or(config_path).to_path_buf();
+            let keys = ["tasks", name.as_str()];
+            let mut at = first.display().to_string();
+            if let Some((line, column)) = linter.source(&first).position(&keys) {
+                at = format!("{at}:{line}:{column}");
+            }
+            let message = format!("Task '{name}' is already defined at {at}");
+            linter.push(&path, &keys, Severity::Error, message);
+            extra.tasks.shift_remove(&name);
+        }
+        config.merge(extra, &path, config_path)?;
+    }
+    config.apply_defaults();
+
+    for problem in config.problems() {
+        let path = config.source(&problem.task).unwrap_or(config_path);
+        let mut keys = vec!["tasks", problem.task.as_str()];
+        keys.extend(problem.key);
+        linter.push(path, &keys, Severity::Error, problem.error.message());
+    }
+
+    // Settings a task inherits are reported once, where `[defaults]` sets them.
+    let mut reported = HashSet::new();
+    for (name, task) in &config.tasks {
+        let path = config.source(name).unwrap_or(config_path);
+        let effective = task.effective();
+        let mut check = |key, own: bool, severity, message: String| {
+            if own {
+                let message = format!("Task '{name}' {message}");
+                linter.push(path, &["tasks", name, key], severity, message);
+            } else if reported.insert(key) {
+                let message = format!("The default {key} {message}");
+                linter.push(config_path, &["defaults", key], severity, message);
+            }
+        };
+
+        if let Some(env_file) = &effective.env_file {
+            if let Err(e) = read_env_file(env_file) {
+                let message = format!("can't be loaded: {}", e.message());
+                check(
+                    "env_file",
+                    task.env_file.is_some(),
+                    Severity::Error,
+                    message,
+                );
+            }
+        }
+        if let Some(cwd) = &effective.cwd {
+            if !cwd.is_dir() {
+                let message = format!("runs in {}, which doesn't exist", cwd.display());
+                check("cwd", task.cwd.is_some(), Severity::Warning, message);
+            }
+        }
+        let notifies = effective
+            .notify_on
+            .as_ref()
+            .is_some_and(|on| !on.is_empty());
+        if notifies && config.notifiers.is_empty() {
+            let message = "sends notifications, but no notifiers are set up".to_string();
+            check(
+                "notify_on",
+                task.notify_on.is_some(),
+                Severity::Warning,
+                message,
+            );
+        }
+    }
+
+    let mut issues = linter.issues;
+    issues.sort_by(|a, b| (&a.path, a.position).cmp(&(&b.path, b.position)));
+    Ok(issues)
+}
+
+fn table_span(table: &dyn TableLike, keys: &[&str]) -> Option<Range<usize>> {
+    let (first, rest) = keys.split_first()?;
+    let (key, item) = table.get_key_value(first)?;
+    item_span(item, rest)
+        .or_else(|| key.span())
+        .or_else(|| item.span())
+}
+
+fn item_span(item: &Item, keys: &[&str]) -> Option<Range<usize>> {
+    if let Some(table) = item.as_table_like() {
+        return table_span(table, keys);
+    }
+
+    let (index, rest) = keys.split_first()?;
+    let index: usize = index.parse().ok()?;
+    match item {
+        Item::ArrayOfTables(tables) => {
+            let table = tables.get(index)?;
+            table_span(table, rest).or_else(|| table.span())
+        }
+        Item::Value(Value::Array(values)) => values.get(index)?.span(),
+        _ => None,
+    }
+}
+
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lines_and_columns_from_one() {
+        let text = "a = 1\nbé = 2\n";
+        assert_eq!(line_column(text, 0), (1, 1));
+        assert_eq!(line_column(text, 6), (2, 1));
+        assert_eq!(line_column(text, 9), (2, 3));
+        assert_eq!(line_column(text, 1000), (3, 1));
+    }
+
+    #[test]
+    fn finds_the_span_of_nested_keys() {
+        let text = "version = 1\n\n\
+                    [tasks.a]\n\
+                    name = \"a\"\n\
+                    depends_on = [\"b\", \"c\"]\n\n\
+                    [[tasks.a.steps]]\n\
+                    command = \"one\"\n\n\
+                    [[tasks.a.steps]]\n\
+                    command = \"two\"\n";
+        let document = ImDocument::parse(text.to_string()).unwrap();
+        let position = |keys: &[&str]| {
+            table_span(document.as_table(), keys).map(|span| line_column(text, span.start))
+        };
+
+        assert_eq!(position(&["version"]), Some((1, 1)));
+        assert_eq!(position(&["tasks", "a", "name"]), Some((4, 1)));
+        assert_eq!(position(&["tasks", "a", "depends_on", "1"]), Some((5, 20)));
+        assert_eq!(
+            position(&["tasks", "a", "steps", "1", "command"]),
+            Some((11, 1))
+        );
+        // Missing keys fall back to the closest parent that exists.
+        assert_eq!(
+            position(&["tasks", "a", "timeout"]),
+            position(&["tasks", "a"])
+        );
+        assert_eq!(position(&["defaults"]), None);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crony-lint-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("tasks.d")).unwrap();
+        dir
+    }
+
+    fn messages(issues: &[Issue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.message.as_str()).collect()
+    }
+
+    #[test]
+    fn only_warns_about_the_main_files_version() {
+        let dir = temp_dir("version");
+        let task = "name = \"a\"\nschedule = \"0 0 * * * *\"\ncommand = \"true\"\n";
+        fs::write(dir.join("tasks.toml"), "version = 1\n").unwrap();
+        fs::write(dir.join("tasks.d/a.toml"), format!("[tasks.a]\n{task}")).unwrap();
+        let issues = lint_from(&dir.join("tasks.toml")).unwrap();
+        assert!(issues.is_empty(), "{:?}", messages(&issues));
+
+        fs::write(dir.join("tasks.toml"), "").unwrap();
+        let issues = lint_from(&dir.join("tasks.toml")).unwrap();
+        let message =
+            format!("Config version 0 is out of date, crony reads it as version {CONFIG_VERSION}");
+        assert_eq!(messages(&issues), [message]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_notifier_keys() {
+        let dir = temp_dir("notifier");
+        let path = dir.join("tasks.toml");
+        for notifier in [
+            "type = \"desktop\"\nurgncy = \"x\"",
+            "type = \"mail\"\nto = \"ops@example.com\"\nfrmo = \"crony@example.com\"",
+        ] {
+            fs::write(
+                &path,
+                format!("version = 1\n\n[notifiers.one]\n{notifier}\n"),
+            )
+            .unwrap();
+            let issues = lint_from(&path).unwrap();
+            assert_eq!(issues.len(), 1, "{:?}", messages(&issues));
+            assert_eq!(issues[0].severity, Severity::Error);
+            assert!(
+                issues[0].message.contains("unknown field"),
+                "{}",
+                issues[0].message
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::errors::{CronyError, Result};
+use clap::{Args, ValueEnum};
+use std::{
+    fs,
+    fs::OpenOptions,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::Arc,
+};
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, EnvFilter};
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct LogArgs {
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// Log level or filter directives, e.g. `debug` or `crony=trace`.
+    /// Defaults to `RUST_LOG`, then `info`
+    #[arg(long, global = true, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Append logs to this file instead of writing them to stderr
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LogFormat {
+    #[default]
+    Full,
+    Compact,
+    Pretty,
+    Json,
+}
+
+pub fn init(args: &LogArgs) -> Result<()> {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level),
+        None => EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info")),
+    }
+    .map_err(|e| CronyError::Cli(format!("Invalid log level: {e}")))?;
+
+    let writer = match &args.log_file {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            BoxMakeWriter::new(Arc::new(file))
+        }
+        None => BoxMakeWriter::new(io::stderr),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(args.log_file.is_none() && io::stderr().is_terminal());
+
+    match args.log_format {
+        LogFormat::Full => builder.init(),
+        LogFormat::Compact => builder.compact().init(),
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+
+    Ok(())
+}
@@ -1,19 +1,35 @@
 mod cli;
 mod config;
+mod crontab;
 mod errors;
+mod export;
+mod healthcheck;
+mod history;
 mod interactive;
+mod lint;
+mod logging;
+mod metrics;
+mod migrate;
+mod notify;
 mod parser;
+mod service;
+mod state;
 mod task;
+mod trash;
+mod tui;
+mod webhook;
 mod worker;
 
-use cli::handle_cli_command;
+use cli::{handle_cli_command, ListFormat, TaskFilter};
 use config::TaskConfig;
-use errors::Result;
+use errors::{CronyError, Result};
 use interactive::handle_interactive_mode;
+use logging::LogArgs;
 
-use clap::{Parser, Subcommand};
-use cliclack::{intro, select};
+use clap::{Args, Parser, Subcommand};
+use cliclack::{intro, log, outro, select};
 use console::style;
+use std::{net::SocketAddr, path::PathBuf};
 
 #[derive(Parser)]
 #[command(name = "crony")]
@@ -21,19 +37,275 @@ use console::style;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Config file to use instead of the default. Can also be set with
+    /// `CRONY_CONFIG`
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    log: LogArgs,
 }
 
 #[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
 pub enum Commands {
     Create,
-    List,
-    Delete,
+    List(ListArgs),
+    /// Print a task's definition
+    Show(ShowArgs),
+    /// Delete tasks, keeping them in the trash
+    Delete(DeleteArgs),
     Edit,
-    Run,
+    Run(RunArgs),
+    /// Show the state of each task and the running scheduler
+    Status(StatusArgs),
+    /// Manage tasks in a full-screen terminal UI
+    Tui,
+    /// Move a task to another position in the list
+    Move(MoveArgs),
+    /// Stop tasks from running until they are resumed
+    Pause(TasksArgs),
+    /// Resume paused tasks
+    Resume(TasksArgs),
+    /// Run tasks once now
+    Trigger(TasksArgs),
+    /// Add a tag to tasks, or remove it
+    Tag(TagArgs),
+    /// Rename a task, keeping its history
+    Rename(RenameArgs),
+    /// Copy a task under a new name
+    Duplicate(RenameArgs),
+    /// Undo the last deletion or change of tasks
+    Undo,
+    /// Bring back the last deleted or changed version of a task
+    Restore(RestoreArgs),
+    /// Check the config files for mistakes
+    Validate(ValidateArgs),
+    /// Add tasks from another scheduler's configuration
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Write tasks out for another scheduler
+    #[command(subcommand)]
+    Export(ExportCommand),
+    /// Run crony in the background as a systemd service
+    #[command(subcommand)]
+    Service(ServiceCommand),
+    /// Inspect crony's configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
+pub enum ConfigCommand {
+    /// Print the path of the config file in use
+    Path,
+    /// Print a JSON Schema of the config file, for editors
+    Schema,
+}
+
+#[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
+pub enum ImportCommand {
+    /// Add the jobs in a crontab as tasks
+    Crontab(ImportCrontabArgs),
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImportCrontabArgs {
+    /// Crontab file to read, or `-` for stdin. Defaults to the output of
+    /// `crontab -l`
+    #[arg(value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Read the file as a system crontab, with a user field before each
+    /// command. Assumed for /etc/crontab and files in /etc/cron.d
+    #[arg(long)]
+    pub system: bool,
+
+    /// Print the tasks that would be added instead of adding them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
+pub enum ExportCommand {
+    /// Print tasks as crontab lines
+    Crontab(ExportCrontabArgs),
+    /// Print tasks as systemd service and timer units, or write them to a
+    /// directory
+    Systemd(ExportSystemdArgs),
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExportCrontabArgs {
+    /// Task names or numbers in `crony list`. Defaults to all tasks
+    pub tasks: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExportSystemdArgs {
+    /// Task names or numbers in `crony list`. Defaults to all tasks
+    pub tasks: Vec<String>,
+
+    /// Directory to write the unit files to, such as ~/.config/systemd/user
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
+pub enum ServiceCommand {
+    /// Write a unit that runs `crony run` with this binary and config file
+    Install(ServiceChangeArgs),
+    /// Remove the unit written by `crony service install`
+    Uninstall(ServiceChangeArgs),
+    /// Show whether the unit is installed, up to date and running
+    Status(ServiceArgs),
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ServiceArgs {
+    /// Use a service of the current user. This is the default
+    #[arg(long, conflicts_with = "system")]
+    pub user: bool,
+
+    /// Use a service of the whole system, which usually needs root
+    #[arg(long)]
+    pub system: bool,
+
+    /// Directory of the unit file, instead of the one systemd reads
+    #[arg(long, value_name = "DIR")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ServiceChangeArgs {
+    #[command(flatten)]
+    pub service: ServiceArgs,
+
+    /// Run the systemctl commands that start or stop the service, instead of
+    /// printing them
+    #[arg(long)]
+    pub now: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ListArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ListFormat,
+
+    /// Only show tasks matching `tag=<tag>` or
+    /// `status=<success|failure|timeout|never>`. Can be repeated
+    #[arg(long, value_name = "FILTER")]
+    pub filter: Vec<TaskFilter>,
+
+    /// Show which config file each task is defined in
+    #[arg(long)]
+    pub show_source: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TasksArgs {
+    /// Task names or numbers in `crony list`
+    #[arg(required = true)]
+    pub tasks: Vec<String>,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeleteArgs {
+    /// Task names or numbers in `crony list`
+    #[arg(required = true)]
+    pub tasks: Vec<String>,
+
+    /// Don't ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShowArgs {
+    /// Task name or number in `crony list`
+    pub task: String,
+
+    /// Fill in the settings the task takes from `[defaults]`
+    #[arg(long)]
+    pub effective: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidateArgs {
+    /// Treat warnings, such as unknown keys, as errors
+    #[arg(long)]
+    pub strict: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RestoreArgs {
+    pub task: String,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagArgs {
+    pub tag: String,
+
+    /// Task names or numbers in `crony list`
+    #[arg(required = true)]
+    pub tasks: Vec<String>,
+
+    /// Remove the tag instead of adding it
+    #[arg(long)]
+    pub remove: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RenameArgs {
+    /// Task name or number in `crony list`
+    pub task: String,
+
+    pub new_name: String,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MoveArgs {
+    /// Task name or number in `crony list`
+    pub task: String,
+
+    /// New position, starting at 1
+    pub position: usize,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct StatusArgs {
+    /// Keep refreshing the view every second
+    #[arg(long)]
+    pub watch: bool,
+}
+
+#[derive(Args, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RunArgs {
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9090
+    #[arg(long, value_name = "ADDR")]
+    pub metrics: Option<SocketAddr>,
+
+    /// Refuse to start if any task has a problem (the default)
+    #[arg(long, conflicts_with = "lenient")]
+    pub strict: bool,
+
+    /// Start anyway, leaving out the tasks that have problems
+    #[arg(long)]
+    pub lenient: bool,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    // The TUI owns the terminal, so only log while it is open if a log file
+    // was given.
+    if cli.command != Some(Commands::Tui) || cli.log.log_file.is_some() {
+        logging::init(&cli.log)?;
+    }
+
+    if let Some(path) = cli.config {
+        config::set_config_path(path);
+    }
 
     if let Some(command) = cli.command {
         return handle_cli_command(command);
@@ -42,14 +314,70 @@ fn main() -> Result<()> {
     intro(style(" crony ").on_cyan().black())?;
 
     let mut config = TaskConfig::load()?;
-    let mode = select("select a mode")
-        .item(Some(Commands::Create), "create a task", "")
-        .item(Some(Commands::List), "list all tasks", "")
-        .item(Some(Commands::Delete), "delete a task", "")
-        .item(Some(Commands::Edit), "edit a task", "")
-        .item(Some(Commands::Run), "run all tasks in background", "")
-        .interact()?;
-
-    handle_interactive_mode(mode, &mut config)?;
+    loop {
+        let mode = select("select a mode")
+            .item(Some(Commands::Create), "create a task", "")
+            .item(
+                Some(Commands::List(ListArgs::default())),
+                "list all tasks",
+                "",
+            )
+            .item(
+                Some(Commands::Delete(DeleteArgs::default())),
+                "delete tasks",
+                "",
+            )
+            .item(Some(Commands::Edit), "edit a task", "")
+            .item(
+                Some(Commands::Rename(RenameArgs::default())),
+                "rename a task",
+                "",
+            )
+            .item(
+                Some(Commands::Duplicate(RenameArgs::default())),
+                "duplicate a task",
+                "",
+            )
+            .item(
+                Some(Commands::Pause(TasksArgs::default())),
+                "pause tasks",
+                "",
+            )
+            .item(
+                Some(Commands::Resume(TasksArgs::default())),
+                "resume tasks",
+                "",
+            )
+            .item(
+                Some(Commands::Trigger(TasksArgs::default())),
+                "run tasks now",
+                "",
+            )
+            .item(Some(Commands::Tag(TagArgs::default())), "tag tasks", "")
+            .item(Some(Commands::Undo), "undo the last change", "")
+            .item(
+                Some(Commands::Run(RunArgs::default())),
+                "run all tasks in background",
+                "",
+            )
+            .item(None, "quit", "")
+            .interact();
+
+        // Cancelling the menu itself quits, like picking "quit".
+        let mode = match mode.map_err(CronyError::from) {
+            Ok(Some(mode)) => mode,
+            Ok(None) => break,
+            Err(e) if e.is_cancelled() => break,
+            Err(e) => return Err(e),
+        };
+
+        match handle_interactive_mode(mode, &mut config) {
+            Ok(()) => {}
+            Err(e) if e.is_cancelled() => {}
+            Err(e) => log::error(e)?,
+        }
+    }
+
+    outro("bye")?;
     Ok(())
 }
@@ -0,0 +1,269 @@
+use crate::config::TaskConfig;
+use crate::errors::Result;
+use crate::history::RunRecord;
+use crate::task::TaskOutcome;
+use apalis_cron::Schedule;
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tracing::{error, info};
+
+/// Per-task counters and gauges exported on `/metrics`.
+pub struct Metrics {
+    tasks: Mutex<BTreeMap<String, TaskMetrics>>,
+    schedules: HashMap<String, (Schedule, Tz)>,
+}
+
+#[derive(Default)]
+struct TaskMetrics {
+    runs: u64,
+    failures: u64,
+    timeouts: u64,
+    retries: u64,
+    running: u64,
+    last_success: Option<DateTime<Local>>,
+    last_duration_ms: Option<u64>,
+}
+
+impl Metrics {
+    pub fn new(config: &TaskConfig) -> Self {
+        let tasks = config
+            .tasks
+            .keys()
+            .map(|name| (name.clone(), TaskMetrics::default()))
+            .collect();
+        let schedules = config
+            .tasks
+            .iter()
+            .filter(|(_, task)| !task.paused && !task.is_triggered_only())
+            .filter_map(|(name, task)| {
+                let schedule = (task.get_schedule().ok()?, task.get_timezone().ok()?);
+                Some((name.clone(), schedule))
+            })
+            .collect();
+
+        Self {
+            tasks: Mutex::new(tasks),
+            schedules,
+        }
+    }
+
+    pub fn started(&self, task: &str) {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.entry(task.to_string()).or_default().running += 1;
+    }
+
+    pub fn finished(&self, record: &RunRecord) {
+        let mut tasks = self.tasks.lock().unwrap();
+        let metrics = tasks.entry(record.task.clone()).or_default();
+        metrics.runs += 1;
+        metrics.retries += u64::from(record.retries);
+        metrics.running = metrics.running.saturating_sub(1);
+        metrics.last_duration_ms = Some(record.duration_ms());
+        match record.outcome {
+            TaskOutcome::Success => metrics.last_success = Some(record.finished_at),
+            TaskOutcome::Failure => metrics.failures += 1,
+            TaskOutcome::Timeout => metrics.timeouts += 1,
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let tasks = self.tasks.lock().unwrap();
+        let mut out = String::new();
+
+        for (name, kind, help, sample) in FAMILIES {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (task, metrics) in tasks.iter() {
+                if let Some(value) = sample(self, task, metrics) {
+                    let _ = writeln!(out, "{name}{{task=\"{}\"}} {value}", escape_label(task));
+                }
+            }
+        }
+
+        out
+    }
+
+    fn next_run(&self, task: &str) -> Option<f64> {
+        let (schedule, timezone) = self.schedules.get(task)?;
+        let next = schedule.upcoming(*timezone).next()?;
+        Some(next.timestamp() as f64)
+    }
+}
+
+type Sample = fn(&Metrics, &str, &TaskMetrics) -> Option<f64>;
+
+/// Name, type, help text and per-task value of each exported metric.
+const FAMILIES: &[(&str, &str, &str, Sample)] = &[
+    (
+        "crony_task_runs_total",
+        "counter",
+        "Completed runs of the task.",
+        |_, _, m| Some(m.runs as f64),
+    ),
+    (
+        "crony_task_failures_total",
+        "counter",
+        "Runs that failed.",
+        |_, _, m| Some(m.failures as f64),
+    ),
+    (
+        "crony_task_timeouts_total",
+        "counter",
+        "Runs that timed out.",
+        |_, _, m| Some(m.timeouts as f64),
+    ),
+    (
+        "crony_task_retries_total",
+        "counter",
+        "Retry attempts made after failed or timed-out runs.",
+        |_, _, m| Some(m.retries as f64),
+    ),
+    (
+        "crony_task_running",
+        "gauge",
+        "Runs currently in progress.",
+        |_, _, m| Some(m.running as f64),
+    ),
+    (
+        "crony_task_last_success_timestamp_seconds",
+        "gauge",
+        "Unix time of the last successful run.",
+        |_, _, m| m.last_success.map(|t| t.timestamp() as f64),
+    ),
+    (
+        "crony_task_last_duration_seconds",
+        "gauge",
+        "Duration of the last completed run.",
+        |_, _, m| m.last_duration_ms.map(|ms| ms as f64 / 1000.0),
+    ),
+    (
+        "crony_task_next_run_timestamp_seconds",
+        "gauge",
+        "Unix time of the next scheduled run.",
+        |metrics, task, _| metrics.next_run(task),
+    ),
+];
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serves `metrics` over HTTP at `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving metrics on http://{addr}/metrics");
+
+    loop {
+        let (mut stream, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to accept metrics connection: {e}");
+                continue;
+            }
+        };
+
+        let metrics = Arc::clone(&metrics);
+        tokio::spawn(async move {
+            let mut buf = [0; 1024];
+            let Ok(n) = stream.read(&mut buf).await else {
+                return;
+            };
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+            let response = match path {
+                "/metrics" => {
+                    let body = metrics.render();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                }
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+
+    fn config() -> TaskConfig {
+        let mut config = TaskConfig::default();
+        for (name, paused) in [("backup", false), ("say \"hi\"", true)] {
+            let mut task = Task::new(name.into(), "0 0 * * * *".into(), "true".into());
+            task.paused = paused;
+            config.tasks.insert(name.into(), task);
+        }
+        config
+    }
+
+    fn record(task: &str, outcome: TaskOutcome, retries: u32) -> RunRecord {
+        let finished_at = Local::now();
+        RunRecord {
+            run_id: "run-1".into(),
+            task: task.into(),
+            scheduled_at: None,
+            started_at: finished_at - chrono::Duration::milliseconds(1500),
+            finished_at,
+            outcome,
+            steps: Vec::new(),
+            retries,
+        }
+    }
+
+    fn sample<'a>(rendered: &'a str, name: &str, task: &str) -> Option<&'a str> {
+        let prefix = format!("{name}{{task=\"{task}\"}} ");
+        rendered.lines().find_map(|line| line.strip_prefix(&prefix))
+    }
+
+    #[test]
+    fn renders_counters_and_gauges_per_task() {
+        let metrics = Metrics::new(&config());
+        metrics.started("backup");
+        metrics.finished(&record("backup", TaskOutcome::Failure, 2));
+        metrics.started("backup");
+        metrics.started("backup");
+        metrics.finished(&record("backup", TaskOutcome::Success, 0));
+
+        let rendered = metrics.render();
+        let backup = |name| sample(&rendered, name, "backup");
+        assert_eq!(backup("crony_task_runs_total"), Some("2"));
+        assert_eq!(backup("crony_task_failures_total"), Some("1"));
+        assert_eq!(backup("crony_task_timeouts_total"), Some("0"));
+        assert_eq!(backup("crony_task_retries_total"), Some("2"));
+        assert_eq!(backup("crony_task_running"), Some("1"));
+        assert_eq!(backup("crony_task_last_duration_seconds"), Some("1.5"));
+        assert!(backup("crony_task_last_success_timestamp_seconds").is_some());
+        assert!(backup("crony_task_next_run_timestamp_seconds").is_some());
+        assert!(rendered.contains("# TYPE crony_task_runs_total counter\n"));
+        assert!(rendered.contains("# TYPE crony_task_running gauge\n"));
+    }
+
+    #[test]
+    fn leaves_out_values_a_task_does_not_have() {
+        let rendered = Metrics::new(&config()).render();
+        let paused = |name| sample(&rendered, name, "say \\\"hi\\\"");
+        assert_eq!(paused("crony_task_runs_total"), Some("0"));
+        assert_eq!(paused("crony_task_last_success_timestamp_seconds"), None);
+        assert_eq!(paused("crony_task_next_run_timestamp_seconds"), None);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::config::write_file;
+use crate::errors::{CronyError, Result};
+use std::{fs, path::Path};
+use toml_edit::{value, DocumentMut};
+use tracing::info;
+
+/// The config format this crony reads and writes. Bump it, and add a
+/// migration, whenever a change would stop older files from loading or
+/// change what they mean.
+pub const CONFIG_VERSION: u32 = 1;
+
+type Migration = fn(&mut DocumentMut);
+
+/// `MIGRATIONS[n]` upgrades a file from version `n` to `n + 1`. Migrations
+/// edit the document in place, so comments and formatting are kept.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    // Files from before the `version` key have the same layout as version 1.
+    |_| {},
+];
+
+/// The version of a config file, where files from before the `version` key
+/// are version 0.
+fn version(document: &DocumentMut, path: &Path) -> Result<u32> {
+    let Some(item) = document.get("version") else {
+        return Ok(0);
+    };
+    item.as_integer()
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| {
+            CronyError::Config(format!(
+                "{}: version must be a whole number",
+                path.display()
+            ))
+        })
+}
+
+/// Brings the config in `content` up to [`CONFIG_VERSION`], returning the
+/// version it was at and the upgraded text, or `None` if it is up to date.
+pub fn migrate(content: &str, path: &Path) -> Result<Option<(u32, String)>> {
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| CronyError::Config(format!("{}: {e}", path.display())))?;
+
+    let from = version(&document, path)?;
+    if from > CONFIG_VERSION {
+        return Err(CronyError::Config(format!(
+            "{} is config version {from}, but this crony only understands up to version \
+             {CONFIG_VERSION}, upgrade crony to use it",
+            path.display()
+        )));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(&mut document);
+    }
+    document.insert("version", value(i64::from(CONFIG_VERSION)));
+    Ok(Some((from, document.to_string())))
+}
+
+/// Whether the file at `path` is older than [`CONFIG_VERSION`].
+pub fn is_outdated(path: &Path) -> Result<bool> {
+    let content = fs::read_to_string(path)?;
+    Ok(migrate(&content, path)?.is_some())
+}
+
+/// Upgrades the file at `path` in place, first copying the old file to
+/// `<path>.v<version>.bak`.
+pub fn upgrade_file(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let Some((from, upgraded)) = migrate(&content, path)? else {
+        return Ok(());
+    };
+
+    let mut backup = path.file_name().unwrap_or_default().to_os_string();
+    backup.push(format!(".v{from}.bak"));
+    let backup = path.with_file_name(backup);
+    fs::copy(path, &backup)?;
+    write_file(path, &upgraded)?;
+
+    info!(
+        "Upgraded {} from config version {from} to {CONFIG_VERSION}, the old file is at {}",
+        path.display(),
+        backup.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_the_version_and_keeps_comments() {
+        let old = "# my tasks\n[tasks.a]\nname = \"a\" # first\n";
+        let (from, upgraded) = migrate(old, Path::new("tasks.toml")).unwrap().unwrap();
+        assert_eq!(from, 0);
+        assert!(upgraded.contains("# my tasks"));
+        assert!(upgraded.contains("name = \"a\" # first"));
+        assert!(upgraded.contains(&format!("version = {CONFIG_VERSION}")));
+    }
+
+    #[test]
+    fn leaves_current_files_alone() {
+        let current = format!("version = {CONFIG_VERSION}\n");
+        assert!(migrate(&current, Path::new("tasks.toml"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn refuses_newer_and_invalid_versions() {
+        let newer = format!("version = {}\n", CONFIG_VERSION + 1);
+        let error = migrate(&newer, Path::new("tasks.toml")).unwrap_err();
+        assert!(error.message().contains("upgrade crony"));
+        assert!(migrate("version = -1\n", Path::new("tasks.toml")).is_err());
+        assert!(migrate("version = \"one\"\n", Path::new("tasks.toml")).is_err());
+        assert!(migrate("[tasks\n", Path::new("tasks.toml")).is_err());
+    }
+}
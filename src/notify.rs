@@ -0,0 +1,205 @@
+use crate::errors::{CronyError, Result};
+use crate::history::RunRecord;
+use crate::task::TaskOutcome;
+use crate::webhook::post;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// A destination for run notifications, configured under `[notifiers.<name>]`.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Notifier {
+    /// Desktop notification through `notify-send`. A struct variant, so
+    /// unknown keys are rejected for it as for the others.
+    Desktop {},
+    /// Mail handed to the local `sendmail`.
+    Mail {
+        to: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+        #[serde(default = "default_sendmail")]
+        sendmail: String,
+    },
+    /// Shell command run with the run details in `CRONY_*` environment
+    /// variables.
+    Command { command: String },
+    /// JSON `POST` to an HTTP endpoint.
+    Webhook { url: String },
+}
+
+fn default_sendmail() -> String {
+    "sendmail".into()
+}
+
+impl Notifier {
+    /// Delivers a notification about `record`. Blocks until delivery
+    /// completes.
+    pub fn send(&self, record: &RunRecord) -> Result<()> {
+        let subject = format!("crony: {}", summary(record));
+        let body = details(record);
+
+        match self {
+            Notifier::Desktop {} => {
+                let urgency = match record.outcome {
+                    TaskOutcome::Success => "normal",
+                    TaskOutcome::Failure | TaskOutcome::Timeout => "critical",
+                };
+                run(Command::new("notify-send")
+                    .args(["--app-name", "crony", "--urgency", urgency])
+                    .arg(&subject)
+                    .arg(&body))
+            }
+            Notifier::Mail { to, from, sendmail } => {
+                let mut message = format!("To: {to}\n");
+                if let Some(from) = from {
+                    message.push_str(&format!("From: {from}\n"));
+                }
+                message.push_str(&format!("Subject: {subject}\n\n{body}\n"));
+
+                let mut child = Command::new(sendmail)
+                    .arg("-t")
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| CronyError::Notify(format!("Failed to run '{sendmail}': {e}")))?;
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(message.as_bytes())?;
+                check(sendmail, child.wait()?)
+            }
+            Notifier::Command { command } => run(Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("CRONY_TASK", &record.task)
+                .env("CRONY_RUN_ID", &record.run_id)
+                .env("CRONY_OUTCOME", record.outcome.to_string())
+                .env(
+                    "CRONY_EXIT_CODE",
+                    record
+                        .exit_code()
+                        .map(|c| c.to_string())
+                        .unwrap_or_default(),
+                )
+                .env("CRONY_DURATION_MS", record.duration_ms().to_string())
+                .env("CRONY_MESSAGE", &body)),
+            Notifier::Webhook { url } => {
+                let payload = serde_json::json!({
+                    "task": record.task,
+                    "run_id": record.run_id,
+                    "outcome": record.outcome,
+                    "exit_code": record.exit_code(),
+                    "duration_ms": record.duration_ms(),
+                    "message": subject,
+                    "details": body,
+                });
+                post(url, &payload.to_string(), &[])
+            }
+        }
+    }
+}
+
+fn summary(record: &RunRecord) -> String {
+    let verb = match record.outcome {
+        TaskOutcome::Success => "succeeded",
+        TaskOutcome::Failure => "failed",
+        TaskOutcome::Timeout => "timed out",
+    };
+    format!("task '{}' {verb}", record.task)
+}
+
+fn details(record: &RunRecord) -> String {
+    let mut lines = vec![format!(
+        "run {} finished in {} ms",
+        record.run_id,
+        record.duration_ms()
+    )];
+    for (i, step) in record.steps.iter().enumerate() {
+        let mut line = format!("step {}: {} ({})", i + 1, step.command, step.outcome);
+        if let Some(error) = &step.error {
+            line.push_str(&format!(": {error}"));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| CronyError::Notify(format!("Failed to run '{program}': {e}")))?;
+    check(&program, status)
+}
+
+fn check(program: &str, status: std::process::ExitStatus) -> Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CronyError::Notify(format!(
+            "'{program}' exited with {status}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::StepRecord;
+    use crate::webhook::tests::stand_in;
+    use chrono::Local;
+
+    fn record(outcome: TaskOutcome) -> RunRecord {
+        let now = Local::now();
+        RunRecord {
+            run_id: "run-1".into(),
+            task: "backup".into(),
+            scheduled_at: None,
+            started_at: now,
+            finished_at: now,
+            outcome,
+            steps: vec![StepRecord {
+                command: "./backup.sh".into(),
+                outcome,
+                exit_code: Some(2),
+                duration_ms: 0,
+                error: Some("exited with exit status: 2".into()),
+                output_tail: String::new(),
+            }],
+            retries: 0,
+        }
+    }
+
+    #[test]
+    fn webhook_posts_the_run_as_json() {
+        let (url, server) = stand_in(&[200]);
+        Notifier::Webhook { url }
+            .send(&record(TaskOutcome::Failure))
+            .unwrap();
+
+        let (head, body) = server.join().unwrap().remove(0);
+        assert!(head.starts_with("POST /notify "));
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["task"], "backup");
+        assert_eq!(payload["run_id"], "run-1");
+        assert_eq!(payload["outcome"], "failure");
+        assert_eq!(payload["exit_code"], 2);
+        assert_eq!(payload["message"], "crony: task 'backup' failed");
+        assert!(payload["details"]
+            .as_str()
+            .unwrap()
+            .contains("step 1: ./backup.sh (failure): exited with exit status: 2"));
+    }
+
+    #[test]
+    fn webhook_fails_on_an_error_status() {
+        let (url, server) = stand_in(&[500]);
+        let result = Notifier::Webhook { url }.send(&record(TaskOutcome::Timeout));
+        server.join().unwrap();
+        assert!(matches!(result, Err(CronyError::Notify(_))));
+    }
+}
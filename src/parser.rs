@@ -1,7 +1,9 @@
 use chrono::prelude::*;
 use regex::Regex;
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct CronExpression {
@@ -24,9 +26,12 @@ impl CronExpression {
             day_of_week: "*".to_string(),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!(
+impl fmt::Display for CronExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
             "{} {} {} {} {} {}",
             self.second, self.minute, self.hour, self.day_of_month, self.month, self.day_of_week
         )
@@ -328,3 +333,69 @@ pub fn parse_natural_language(input: &str) -> Result<CronExpression, String> {
 
     Err(format!("Could not parse '{input}' into a cron expression",))
 }
+
+/// Parses durations such as `90`, `30s`, `5 min` or `1h30m`. Bare numbers are
+/// treated as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim().to_lowercase();
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"^(\d+)\s*([a-z]+)\s*").unwrap());
+    let (mut total, mut rest) = match input.parse::<u64>() {
+        Ok(seconds) => (seconds, ""),
+        Err(_) => (0, input.as_str()),
+    };
+
+    while !rest.is_empty() {
+        let Some(caps) = re.captures(rest) else {
+            return Err(format!("Could not parse '{input}' into a duration"));
+        };
+        let value = caps[1].parse::<u64>().map_err(|e| e.to_string())?;
+        let unit = match &caps[2] {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 24 * 60 * 60,
+            unit => return Err(format!("Unknown duration unit '{unit}' in '{input}'")),
+        };
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(|| format!("Duration '{input}' is too long"))?;
+        rest = &rest[caps[0].len()..];
+    }
+
+    if total == 0 {
+        return Err(format!("Duration '{input}' must be greater than zero"));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        let secs = |input| parse_duration(input).map(|duration| duration.as_secs());
+        assert_eq!(secs("90"), Ok(90));
+        assert_eq!(secs("30s"), Ok(30));
+        assert_eq!(secs("5 min"), Ok(300));
+        assert_eq!(secs("1h30m"), Ok(5400));
+        assert_eq!(secs(" 2 Days "), Ok(2 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("5 fortnights").is_err());
+        assert!(parse_duration("5m later").is_err());
+        assert_eq!(
+            parse_duration("999999999999999d"),
+            Err("Duration '999999999999999d' is too long".into())
+        );
+        assert!(parse_duration("18446744073709551615s 1s").is_err());
+    }
+}
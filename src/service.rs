@@ -0,0 +1,229 @@
+use crate::config::{get_config_path, get_systemd_user_dir, write_file};
+use crate::errors::{CronyError, Result};
+use crate::export::unit_quote;
+use std::{
+    env,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+pub const UNIT_NAME: &str = "crony.service";
+
+/// Whether crony runs as a service of the logged-in user or of the whole
+/// system.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Scope {
+    User,
+    System,
+}
+
+impl Scope {
+    /// Where systemd looks for units of this scope.
+    pub fn unit_dir(self) -> Result<PathBuf> {
+        match self {
+            Scope::User => get_systemd_user_dir(),
+            Scope::System => Ok(PathBuf::from("/etc/systemd/system")),
+        }
+    }
+
+    /// The target the service is started with.
+    fn target(self) -> &'static str {
+        match self {
+            Scope::User => "default.target",
+            Scope::System => "multi-user.target",
+        }
+    }
+
+    /// A `systemctl` command line for this scope.
+    pub fn systemctl(self, args: &[&str]) -> Vec<String> {
+        let mut command = vec!["systemctl".to_string()];
+        if self == Scope::User {
+            command.push("--user".into());
+        }
+        command.extend(args.iter().map(|arg| arg.to_string()));
+        command
+    }
+}
+
+/// The unit that runs `crony run` from this binary with this config file.
+/// System services run as the user installing them, which under `sudo` is
+/// the user who ran it, reading that user's config file.
+pub fn unit(scope: Scope) -> Result<String> {
+    let binary = env::current_exe()?;
+    let mut config = std::path::absolute(get_config_path()?)?;
+    let user = match scope {
+        Scope::User => None,
+        Scope::System => installing_user(),
+    };
+
+    // Under sudo, a config file in root's home stands for the same file in
+    // the user's home.
+    let homes = env::var_os("HOME").zip(user.as_deref().and_then(home_dir));
+    if let Some((root_home, user_home)) = homes {
+        if let Ok(rest) = config.strip_prefix(&root_home) {
+            config = user_home.join(rest);
+        }
+    }
+    Ok(render_unit(scope, &binary, &config, user.as_deref()))
+}
+
+fn render_unit(scope: Scope, binary: &Path, config: &Path, user: Option<&str>) -> String {
+    let exec_start = [
+        binary.to_string_lossy().as_ref(),
+        "--config",
+        config.to_string_lossy().as_ref(),
+        "run",
+    ]
+    .map(|word| unit_quote(word, true))
+    .join(" ");
+
+    let mut unit = format!(
+        "[Unit]\nDescription=Crony Scheduler Service\nAfter=network.target\n\n\
+         [Service]\nExecStart={exec_start}\nRestart=always\nRestartSec=5\n"
+    );
+    if let Some(user) = user {
+        writeln!(unit, "User={user}").unwrap();
+    }
+    write!(unit, "\n[Install]\nWantedBy={}\n", scope.target()).unwrap();
+    unit
+}
+
+/// The user running `sudo`, or else the current user, unless that is root.
+fn installing_user() -> Option<String> {
+    ["SUDO_USER", "USER"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|user| !user.is_empty()))
+        .filter(|user| user != "root")
+}
+
+/// The home directory of `user`, from the password database.
+#[cfg(unix)]
+fn home_dir(user: &str) -> Option<PathBuf> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    let name = CString::new(user).ok()?;
+    // SAFETY: `passwd` is plain data that `getpwnam_r` fills in.
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0; 16 * 1024];
+    let mut result = std::ptr::null_mut();
+    // SAFETY: every pointer is valid for the call. The strings in `passwd`
+    // point into `buffer`, which outlives them.
+    let code = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if code != 0 || result.is_null() || passwd.pw_dir.is_null() {
+        return None;
+    }
+    // SAFETY: `pw_dir` is a nul-terminated string in `buffer`.
+    let dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
+    Some(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+}
+
+#[cfg(not(unix))]
+fn home_dir(_user: &str) -> Option<PathBuf> {
+    None
+}
+
+/// What `install` did to the unit file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Installed {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Writes `unit` to `dir`, leaving the file alone if it already matches.
+pub fn install(dir: &Path, unit: &str) -> Result<Installed> {
+    let path = dir.join(UNIT_NAME);
+    let installed = match fs::read_to_string(&path) {
+        Ok(current) if current == unit => return Ok(Installed::Unchanged),
+        Ok(_) => Installed::Updated,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Installed::Created,
+        Err(e) => return Err(e.into()),
+    };
+    fs::create_dir_all(dir)?;
+    write_file(&path, unit)?;
+    Ok(installed)
+}
+
+/// Removes the unit file from `dir`, along with the link `systemctl enable`
+/// made to it. Returns whether there was a unit to remove.
+pub fn uninstall(dir: &Path, scope: Scope) -> Result<bool> {
+    let wants = dir
+        .join(format!("{}.wants", scope.target()))
+        .join(UNIT_NAME);
+    if wants.symlink_metadata().is_ok() {
+        fs::remove_file(wants)?;
+    }
+    match fs::remove_file(dir.join(UNIT_NAME)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Runs a `systemctl` command line, failing if it does.
+pub fn run(command: &[String]) -> Result<()> {
+    let status = Command::new(&command[0]).args(&command[1..]).status()?;
+    if !status.success() {
+        return Err(CronyError::Cli(format!(
+            "'{}' failed with {status}",
+            command.join(" ")
+        )));
+    }
+    Ok(())
+}
+
+/// What `systemctl` says about the service, such as `enabled` or `active`,
+/// or `None` if it can't be asked.
+pub fn query(scope: Scope, check: &str) -> Option<String> {
+    let command = scope.systemctl(&[check, UNIT_NAME]);
+    let output = Command::new(&command[0])
+        .args(&command[1..])
+        .output()
+        .ok()?;
+    let answer = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(answer).filter(|answer| !answer.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_unit_runs_this_binary_with_the_config() {
+        let unit = render_unit(
+            Scope::User,
+            Path::new("/opt/crony/bin/crony"),
+            Path::new("/home/me/my tasks.toml"),
+            None,
+        );
+        assert!(unit
+            .contains("ExecStart=/opt/crony/bin/crony --config \"/home/me/my tasks.toml\" run\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+        assert!(!unit.contains("User="));
+    }
+
+    #[test]
+    fn system_unit_runs_as_the_installing_user() {
+        let unit = render_unit(
+            Scope::System,
+            Path::new("/usr/local/bin/crony"),
+            Path::new("/home/me/.config/crony/tasks.toml"),
+            Some("me"),
+        );
+        assert!(unit.contains("User=me\n"));
+        assert!(unit.contains("WantedBy=multi-user.target\n"));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::{get_state_dir, write_file};
+use crate::errors::Result;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// How often a running scheduler rewrites its state file.
+pub const HEARTBEAT_SECS: i64 = 5;
+
+/// Live state published by `crony run` so other invocations can see what it
+/// is doing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SchedulerState {
+    pub pid: u32,
+    pub started_at: DateTime<Local>,
+    pub updated_at: DateTime<Local>,
+    /// Runs in progress, keyed by run id.
+    #[serde(default)]
+    pub running: BTreeMap<String, RunningTask>,
+    /// Tasks left out by `crony run --lenient` because of config problems,
+    /// and why.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub disabled: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunningTask {
+    pub task: String,
+    pub started_at: DateTime<Local>,
+}
+
+impl SchedulerState {
+    /// Fresh state for the current process.
+    pub fn current() -> Self {
+        let now = Local::now();
+        Self {
+            pid: std::process::id(),
+            started_at: now,
+            updated_at: now,
+            running: BTreeMap::new(),
+            disabled: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the state of the last scheduler that ran, if any.
+    pub fn load() -> Result<Option<Self>> {
+        let path = state_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        self.updated_at = Local::now();
+        // Written atomically, as other crony processes read it at any time.
+        write_file(&state_path()?, &serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn clear() -> Result<()> {
+        let path = state_path()?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the scheduler that wrote this state is still sending
+    /// heartbeats.
+    pub fn is_live(&self) -> bool {
+        Local::now() - self.updated_at < Duration::seconds(HEARTBEAT_SECS * 3)
+    }
+
+    /// The in-progress run of `task` that started first.
+    pub fn running(&self, task: &str) -> Option<&RunningTask> {
+        self.running
+            .values()
+            .filter(|run| run.task == task)
+            .min_by_key(|run| run.started_at)
+    }
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("scheduler.json"))
+}
@@ -1,16 +1,165 @@
 use crate::errors::{CronyError, Result};
-use crate::parser::parse_natural_language;
+use crate::history::{new_run_id, RunRecord, StepRecord};
+use crate::parser::{parse_duration, parse_natural_language};
 use apalis_cron::Schedule;
 use chrono::{DateTime, Local};
+use chrono_tz::Tz;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{future::Future, str::FromStr};
-use tracing::{error, info};
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    future::Future,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::Command,
+};
+use tracing::{error, info, warn};
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Task {
     pub name: String,
+    /// Cron or natural-language schedule. Left empty for tasks that only run
+    /// when triggered by another task.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub schedule: String,
+    /// Single command to run. Ignored when `steps` is set.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
+    /// Ordered commands run one after another instead of `command`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+    /// Program and arguments each command is passed to, `sh -c` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Environment variables set for each command, on top of crony's own.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub env: IndexMap<String, String>,
+    /// File of `KEY=value` lines loaded before `env`, which wins for the same
+    /// variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<PathBuf>,
+    /// Directory the commands run in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Maximum run time of each step that doesn't set its own, e.g. `30s`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Time zone the schedule is read in, e.g. `Europe/Berlin`. UTC by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Reruns the task when a run fails or times out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<Retry>,
+    /// Tasks that must all succeed before this one runs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Tasks to trigger after a successful run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_success: Vec<String>,
+    /// Tasks to trigger after a failed run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_failure: Vec<String>,
+    /// Run outcomes that are sent to the configured notifiers. An empty list
+    /// turns off notifications set in `[defaults]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_on: Option<Vec<TaskOutcome>>,
+    /// Healthcheck URL pinged at `/start`, on success, and at `/fail`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<String>,
+    /// Free-form labels for grouping and filtering tasks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Paused tasks stay in the config but are never run.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// The config's `[defaults]`, used for the settings the task leaves out.
+    #[serde(skip)]
+    pub defaults: TaskDefaults,
+}
+
+/// Settings from the `[defaults]` table, used by every task that doesn't set
+/// them itself.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Merged with each task's own `env`, which wins for the same variable.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub env: IndexMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<Retry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_on: Option<Vec<TaskOutcome>>,
+}
+
+impl TaskDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Retry {
+    /// How many times a failed run is retried.
+    pub attempts: u32,
+    /// How long to wait before each retry, e.g. `30s`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<String>,
+}
+
+impl Retry {
+    pub fn get_delay(&self) -> Result<Duration> {
+        self.delay.as_deref().map_or(Ok(Duration::ZERO), |delay| {
+            parse_duration(delay).map_err(CronyError::Task)
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Step {
+    pub command: String,
+    /// Maximum run time, e.g. `30s` or `5m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Keep running later steps, and count the run as successful, if this
+    /// step fails.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_error: bool,
+}
+
+impl Step {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_timeout(&self) -> Result<Option<Duration>> {
+        self.timeout
+            .as_deref()
+            .map(|timeout| parse_duration(timeout).map_err(CronyError::Task))
+            .transpose()
+    }
 }
 
 impl Task {
@@ -19,39 +168,268 @@ impl Task {
             name,
             schedule,
             command,
+            ..Default::default()
         }
     }
 
     pub fn get_schedule(&self) -> Result<Schedule> {
         parse_schedule(&self.schedule)
     }
+
+    /// The time zone the schedule is read in.
+    pub fn get_timezone(&self) -> Result<Tz> {
+        match self.timezone.as_ref().or(self.defaults.timezone.as_ref()) {
+            Some(name) => name
+                .parse()
+                .map_err(|_| CronyError::Schedule(format!("Unknown time zone '{name}'"))),
+            None => Ok(Tz::UTC),
+        }
+    }
+
+    /// The task with the settings it leaves out filled in from `[defaults]`.
+    pub fn effective(&self) -> Task {
+        let defaults = &self.defaults;
+        let mut env = defaults.env.clone();
+        env.extend(self.env.clone());
+        Task {
+            shell: self.shell.clone().or_else(|| defaults.shell.clone()),
+            env,
+            env_file: self.env_file.clone().or_else(|| defaults.env_file.clone()),
+            cwd: self.cwd.clone().or_else(|| defaults.cwd.clone()),
+            timeout: self.timeout.clone().or_else(|| defaults.timeout.clone()),
+            timezone: self.timezone.clone().or_else(|| defaults.timezone.clone()),
+            retry: self.retry.clone().or_else(|| defaults.retry.clone()),
+            notify_on: self
+                .notify_on
+                .clone()
+                .or_else(|| defaults.notify_on.clone()),
+            defaults: TaskDefaults::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the task has no schedule of its own and only runs when
+    /// triggered by another task.
+    pub fn is_triggered_only(&self) -> bool {
+        self.schedule.trim().is_empty()
+    }
+
+    /// When the schedule next fires, if the task is scheduled and not paused.
+    pub fn next_run(&self) -> Option<DateTime<Local>> {
+        if self.paused || self.is_triggered_only() {
+            return None;
+        }
+        let timezone = self.get_timezone().ok()?;
+        let next = self.get_schedule().ok()?.upcoming(timezone).next()?;
+        Some(next.with_timezone(&Local))
+    }
+
+    /// The steps to run, treating a plain `command` as a single step. Steps
+    /// without a timeout of their own get the task's.
+    pub fn get_steps(&self) -> Vec<Step> {
+        let mut steps = if self.steps.is_empty() {
+            vec![Step::new(self.command.clone())]
+        } else {
+            self.steps.clone()
+        };
+        for step in &mut steps {
+            step.timeout = step.timeout.take().or_else(|| self.timeout.clone());
+        }
+        steps
+    }
+
+    /// One-line description of what the task runs, for listings.
+    pub fn command_summary(&self) -> String {
+        if self.steps.is_empty() {
+            self.command.clone()
+        } else {
+            self.steps
+                .iter()
+                .map(|step| step.command.as_str())
+                .collect::<Vec<_>>()
+                .join(" && ")
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let name = &self.name;
+        match (self.command.trim().is_empty(), self.steps.is_empty()) {
+            (true, true) => {
+                return Err(CronyError::Task(format!("Task '{name}' has no command")));
+            }
+            (false, false) => {
+                return Err(CronyError::Task(format!(
+                    "Task '{name}' sets both 'command' and 'steps'"
+                )));
+            }
+            _ => {}
+        }
+
+        for (i, step) in self.steps.iter().enumerate() {
+            if step.command.trim().is_empty() {
+                return Err(CronyError::Task(format!(
+                    "Step {} of task '{name}' has no command",
+                    i + 1
+                )));
+            }
+            step.get_timeout()
+                .map_err(|e| CronyError::Task(format!("Step {} of task '{name}': {e}", i + 1)))?;
+        }
+
+        let task = self.effective();
+        if task
+            .shell
+            .as_deref()
+            .is_some_and(|shell| shell.trim().is_empty())
+        {
+            return Err(CronyError::Task(format!(
+                "Task '{name}' has an empty shell"
+            )));
+        }
+        let timeout = task.timeout.as_deref().map(parse_duration).transpose();
+        timeout.map_err(|e| CronyError::Task(format!("Task '{name}': {e}")))?;
+        task.get_timezone()
+            .map_err(|e| CronyError::Task(format!("Task '{name}': {e}")))?;
+        if let Some(retry) = &task.retry {
+            retry
+                .get_delay()
+                .map_err(|e| CronyError::Task(format!("Retry delay of task '{name}': {e}")))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl TaskExecutor for Task {
-    fn execute(&self, _: CronArgument) -> impl Future<Output = ()> + Send {
-        let command = self.command.clone();
-        let name = self.name.clone();
+    fn execute(&self, argument: CronArgument) -> impl Future<Output = RunRecord> + Send {
+        let task = self.effective();
 
         async move {
-            info!("Executing task '{name}' with command: {command}");
-            if let Err(e) = run_command(&command) {
-                error!("Failed to execute task '{name}': {e}");
+            let started_at = Local::now();
+            let retry = task.retry.clone().unwrap_or_default();
+            let mut retries = 0;
+
+            loop {
+                let (outcome, steps) = task.run_steps().await;
+                if outcome == TaskOutcome::Success || retries >= retry.attempts {
+                    return RunRecord {
+                        run_id: argument.run_id,
+                        task: task.name,
+                        scheduled_at: argument.scheduled_at,
+                        started_at,
+                        finished_at: Local::now(),
+                        outcome,
+                        steps,
+                        retries,
+                    };
+                }
+
+                retries += 1;
+                warn!(
+                    "Retrying task '{}' after {outcome} ({retries}/{})",
+                    task.name, retry.attempts
+                );
+                tokio::time::sleep(retry.get_delay().unwrap_or_default()).await;
+            }
+        }
+    }
+}
+
+impl Task {
+    /// Runs the steps once, stopping at the first failure unless the step
+    /// allows it.
+    async fn run_steps(&self) -> (TaskOutcome, Vec<StepRecord>) {
+        let name = &self.name;
+        let steps = self.get_steps();
+        let multi_step = !self.steps.is_empty();
+        let mut outcome = TaskOutcome::Success;
+        let mut records = Vec::new();
+
+        for (i, step) in steps.iter().enumerate() {
+            if multi_step {
+                info!(
+                    "Executing task '{name}' step {}/{}: {}",
+                    i + 1,
+                    steps.len(),
+                    step.command
+                );
+            } else {
+                info!("Executing task '{name}' with command: {}", step.command);
+            }
+
+            let record = run_step(self, step).await;
+            let step_outcome = record.outcome;
+            let error = record.error.clone().unwrap_or_default();
+            records.push(record);
+
+            if step_outcome == TaskOutcome::Success {
+                continue;
+            }
+
+            if !multi_step {
+                error!("Failed to execute task '{name}': {error}");
+                outcome = step_outcome;
+                break;
+            }
+
+            error!("Task '{name}' step {} failed: {error}", i + 1);
+            if step.continue_on_error {
+                warn!("Continuing task '{name}' after failed step {}", i + 1);
+            } else {
+                outcome = step_outcome;
+                break;
             }
         }
+
+        (outcome, records)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskOutcome {
+    Success,
+    Failure,
+    Timeout,
+}
+
+impl fmt::Display for TaskOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TaskOutcome::Success => "success",
+            TaskOutcome::Failure => "failure",
+            TaskOutcome::Timeout => "timeout",
+        })
     }
 }
 
 #[derive(Default, Debug, Clone)]
-pub struct CronArgument;
+pub struct CronArgument {
+    pub run_id: String,
+    /// When the run was due; `None` for runs triggered by another task.
+    pub scheduled_at: Option<DateTime<Local>>,
+}
+
+impl CronArgument {
+    pub fn triggered() -> Self {
+        Self {
+            run_id: new_run_id(&Local::now()),
+            scheduled_at: None,
+        }
+    }
+}
 
 impl From<DateTime<Local>> for CronArgument {
-    fn from(_value: DateTime<Local>) -> Self {
-        CronArgument
+    fn from(value: DateTime<Local>) -> Self {
+        Self {
+            run_id: new_run_id(&value),
+            scheduled_at: Some(value),
+        }
     }
 }
 
 pub trait TaskExecutor {
-    fn execute(&self, argument: CronArgument) -> impl Future<Output = ()> + Send;
+    fn execute(&self, argument: CronArgument) -> impl Future<Output = RunRecord> + Send;
 }
 
 pub fn parse_schedule(input: &str) -> Result<Schedule> {
@@ -63,15 +441,308 @@ pub fn parse_schedule(input: &str) -> Result<Schedule> {
     Schedule::from_str(input).map_err(|e| CronyError::Schedule(format!("Invalid schedule: {e}")))
 }
 
-fn run_command(command: &str) -> Result<std::process::ExitStatus> {
-    std::process::Command::new("sh")
-        .arg("-c")
-        .arg(command)
+/// The 6-field cron expression a schedule resolves to.
+pub fn normalize_schedule(input: &str) -> Result<String> {
+    if let Ok(cron) = parse_natural_language(input) {
+        return Ok(cron.to_string());
+    }
+
+    Schedule::from_str(input)
+        .map(|_| input.trim().to_string())
+        .map_err(|e| CronyError::Schedule(format!("Invalid schedule: {e}")))
+}
+
+async fn run_step(task: &Task, step: &Step) -> StepRecord {
+    let started = std::time::Instant::now();
+    let result = match step.get_timeout() {
+        Ok(timeout) => run_command(task, &step.command, timeout).await,
+        Err(e) => Err(e),
+    };
+
+    let (outcome, exit_code, error, output_tail) = match result {
+        Ok((Some(status), tail)) if status.success() => {
+            (TaskOutcome::Success, status.code(), None, tail)
+        }
+        Ok((Some(status), tail)) => (
+            TaskOutcome::Failure,
+            status.code(),
+            Some(format!("exited with {status}")),
+            tail,
+        ),
+        Ok((None, tail)) => (
+            TaskOutcome::Timeout,
+            None,
+            Some(format!(
+                "timed out after {}",
+                step.timeout.as_deref().unwrap_or("")
+            )),
+            tail,
+        ),
+        Err(e) => (
+            TaskOutcome::Failure,
+            None,
+            Some(e.to_string()),
+            String::new(),
+        ),
+    };
+
+    StepRecord {
+        command: step.command.clone(),
+        outcome,
+        exit_code,
+        duration_ms: started.elapsed().as_millis() as u64,
+        error,
+        output_tail,
+    }
+}
+
+/// How much of a command's output is kept for history and webhooks.
+const OUTPUT_TAIL_BYTES: usize = 4096;
+
+/// Whether command output is copied to crony's own stdout and stderr. The TUI
+/// turns this off so runs it triggers don't draw over the screen.
+static FORWARD_OUTPUT: AtomicBool = AtomicBool::new(true);
+
+pub fn set_forward_output(enabled: bool) {
+    FORWARD_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// How long to keep reading output after a command exits.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// The shell commands are run with when a task doesn't choose one.
+pub const DEFAULT_SHELL: &str = "sh -c";
+
+/// Runs `command` through the task's shell, forwarding its output while
+/// keeping the last [`OUTPUT_TAIL_BYTES`] of it. The exit status is `None` if
+/// the command was killed for exceeding `timeout`.
+async fn run_command(
+    task: &Task,
+    command: &str,
+    timeout: Option<Duration>,
+) -> Result<(Option<ExitStatus>, String)> {
+    let shell = task.shell.as_deref().unwrap_or(DEFAULT_SHELL);
+    let mut shell = shell.split_whitespace();
+    let mut child = Command::new(shell.next().unwrap_or("sh"));
+    child.args(shell).arg(command);
+    if let Some(path) = &task.env_file {
+        child.envs(read_env_file(path)?);
+    }
+    child.envs(&task.env);
+    if let Some(cwd) = &task.cwd {
+        child.current_dir(cwd);
+    }
+    #[cfg(unix)]
+    child.process_group(0);
+    let mut child = child
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
-        .map_err(|e| CronyError::Task(format!("Failed to run command '{command}': {e}")))
-        .and_then(|mut child| {
-            child.wait().map_err(|e| {
-                CronyError::Task(format!("Failed to wait for command '{command}': {e}"))
-            })
-        })
+        .map_err(|e| CronyError::Task(format!("Failed to run command '{command}': {e}")))?;
+    let mut group = ProcessGroup(child.id());
+
+    let tail = Arc::new(Mutex::new(VecDeque::with_capacity(OUTPUT_TAIL_BYTES)));
+    let readers = [
+        tokio::spawn(forward_output(
+            child.stdout.take().expect("stdout is piped"),
+            tokio::io::stdout(),
+            Arc::clone(&tail),
+        )),
+        tokio::spawn(forward_output(
+            child.stderr.take().expect("stderr is piped"),
+            tokio::io::stderr(),
+            Arc::clone(&tail),
+        )),
+    ];
+
+    let wait_error = |e: std::io::Error| {
+        CronyError::Task(format!("Failed to wait for command '{command}': {e}"))
+    };
+
+    let status = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => Some(status.map_err(wait_error)?),
+            Err(_) => {
+                group.kill();
+                child.kill().await.map_err(wait_error)?;
+                None
+            }
+        },
+        None => Some(child.wait().await.map_err(wait_error)?),
+    };
+    // Processes left in the background by a command that finished are its
+    // own business.
+    group.0 = None;
+
+    // Background processes started by the command can keep the pipes open
+    // after it exits, so only wait briefly for the remaining output.
+    for mut reader in readers {
+        if status.is_none()
+            || tokio::time::timeout(OUTPUT_GRACE, &mut reader)
+                .await
+                .is_err()
+        {
+            reader.abort();
+        }
+    }
+
+    let tail = tail.lock().unwrap().iter().copied().collect::<Vec<_>>();
+    Ok((status, String::from_utf8_lossy(&tail).into_owned()))
+}
+
+/// The process group a command runs in. Killing it rather than just the
+/// shell stops everything the command started, on a timeout or when the run
+/// is abandoned because crony is shutting down.
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self
+            .0
+            .take()
+            .and_then(|pid| libc::pid_t::try_from(pid).ok())
+        {
+            // SAFETY: `kill` has no memory safety requirements. A negative
+            // pid signals the group the command leads.
+            unsafe {
+                libc::kill(-pid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Reads the `KEY=value` lines of an env file, skipping blank lines and
+/// `#` comments. Values can be quoted, and lines can start with `export`.
+pub fn read_env_file(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        CronyError::Task(format!("Failed to read env file {}: {e}", path.display()))
+    })?;
+
+    let mut vars = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(CronyError::Task(format!(
+                "{}:{}: expected KEY=value",
+                path.display(),
+                i + 1
+            )));
+        };
+        let value = value.trim();
+        let value = [('"', '"'), ('\'', '\'')]
+            .iter()
+            .find_map(|(open, close)| value.strip_prefix(*open)?.strip_suffix(*close))
+            .unwrap_or(value);
+        vars.push((key.trim().to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
+
+async fn forward_output(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    tail: Arc<Mutex<VecDeque<u8>>>,
+) {
+    let mut buf = [0; 1024];
+    while let Ok(n) = reader.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+        if FORWARD_OUTPUT.load(Ordering::Relaxed) {
+            let _ = writer.write_all(&buf[..n]).await;
+            let _ = writer.flush().await;
+        }
+
+        let mut tail = tail.lock().unwrap();
+        tail.extend(&buf[..n]);
+        let excess = tail.len().saturating_sub(OUTPUT_TAIL_BYTES);
+        tail.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TaskConfig;
+
+    fn config() -> TaskConfig {
+        let mut config: TaskConfig = toml::from_str(
+            r#"
+            [defaults]
+            shell = "/bin/bash"
+            timeout = "10m"
+            timezone = "Europe/Paris"
+            env = { LANG = "C", TEAM = "ops" }
+            retry = { attempts = 2 }
+
+            [tasks.plain]
+            name = "plain"
+            schedule = "0 0 * * * *"
+            steps = [{ command = "./a.sh" }, { command = "./b.sh", timeout = "1m" }]
+
+            [tasks.own]
+            name = "own"
+            schedule = "0 0 * * * *"
+            command = "true"
+            shell = "/bin/zsh"
+            timeout = "30s"
+            timezone = "UTC"
+            env = { TEAM = "dev", EXTRA = "1" }
+            "#,
+        )
+        .unwrap();
+        config.apply_defaults();
+        config
+    }
+
+    #[test]
+    fn fills_in_what_a_task_leaves_out() {
+        let task = config().tasks["plain"].effective();
+        assert_eq!(task.shell.as_deref(), Some("/bin/bash"));
+        assert_eq!(task.timeout.as_deref(), Some("10m"));
+        assert_eq!(task.get_timezone().unwrap(), chrono_tz::Europe::Paris);
+        assert_eq!(task.retry.as_ref().map(|retry| retry.attempts), Some(2));
+        assert_eq!(task.env["LANG"], "C");
+        assert!(task.defaults.is_empty());
+
+        let timeouts: Vec<_> = task.get_steps().into_iter().map(|s| s.timeout).collect();
+        assert_eq!(timeouts, [Some("10m".into()), Some("1m".into())]);
+    }
+
+    #[test]
+    fn keeps_what_a_task_sets_itself() {
+        let task = config().tasks["own"].effective();
+        assert_eq!(task.shell.as_deref(), Some("/bin/zsh"));
+        assert_eq!(task.timeout.as_deref(), Some("30s"));
+        assert_eq!(task.get_timezone().unwrap(), Tz::UTC);
+        let env: Vec<_> = task
+            .env
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(env, [("LANG", "C"), ("TEAM", "dev"), ("EXTRA", "1")]);
+    }
+
+    #[test]
+    fn defaults_are_not_written_into_tasks() {
+        let config = config();
+        let saved = toml::to_string(&config.tasks["plain"]).unwrap();
+        assert!(!saved.contains("bash"), "{saved}");
+        assert_eq!(
+            config.tasks["plain"].get_timezone().unwrap(),
+            chrono_tz::Europe::Paris
+        );
+    }
 }
@@ -0,0 +1,271 @@
+use crate::config::{get_config_state_dir, write_file};
+use crate::errors::Result;
+use crate::task::Task;
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+/// How many changes are kept in the trash before the oldest are dropped.
+const TRASH_LIMIT: usize = 100;
+
+/// The task definitions removed or replaced by a single change to the
+/// config, and the tasks it added, so the change can be undone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Batch {
+    pub at: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<TrashedTask>,
+    /// Tasks that didn't exist before the change.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    /// Whether this holds what an undo or restore replaced. `undo` skips
+    /// these, so running it again goes further back instead of redoing.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub undo: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrashedTask {
+    /// Where the task was in the task order.
+    pub position: usize,
+    /// Whether the task was deleted, rather than changed.
+    pub deleted: bool,
+    pub task: Task,
+}
+
+impl Batch {
+    /// Reverts the change: removes the tasks it added and puts back the
+    /// definitions it removed or replaced.
+    pub fn undo_into(self, tasks: &mut IndexMap<String, Task>) {
+        for name in &self.added {
+            tasks.shift_remove(name);
+        }
+        for trashed in self.tasks {
+            trashed.restore_into(tasks);
+        }
+    }
+
+    /// What undoing the change does, e.g. `restored a, b and removed c`.
+    pub fn summary(&self) -> String {
+        let restored: Vec<_> = self.tasks.iter().map(|t| t.task.name.as_str()).collect();
+        [
+            ("restored", restored),
+            ("removed", self.added.iter().map(String::as_str).collect()),
+        ]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(verb, names)| format!("{verb} {}", names.join(", ")))
+        .collect::<Vec<_>>()
+        .join(" and ")
+    }
+}
+
+impl TrashedTask {
+    /// Puts this definition back, replacing the task if it exists and
+    /// otherwise returning it to its old position.
+    pub fn restore_into(self, tasks: &mut IndexMap<String, Task>) {
+        let name = self.task.name.clone();
+        if tasks.contains_key(&name) {
+            tasks.insert(name, self.task);
+        } else {
+            tasks.shift_insert(self.position.min(tasks.len()), name, self.task);
+        }
+    }
+}
+
+/// The trash of the config file in use. Changes to it are only written by
+/// [`Trash::save`], which callers do while holding the config lock.
+pub struct Trash {
+    batches: Vec<Batch>,
+    changed: bool,
+}
+
+impl Trash {
+    pub fn load() -> Result<Self> {
+        let path = trash_path()?;
+        let batches = if path.exists() {
+            fs::read_to_string(path)?
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            batches,
+            changed: false,
+        })
+    }
+
+    /// Stores the definitions in `before` that are missing or different in
+    /// `after`, along with the names of the tasks `after` added. `undo` marks
+    /// the batch as replaced by an undo or restore.
+    pub fn record(
+        &mut self,
+        before: &IndexMap<String, Task>,
+        after: &IndexMap<String, Task>,
+        undo: bool,
+    ) {
+        let tasks: Vec<_> = before
+            .values()
+            .enumerate()
+            .filter(|(_, task)| after.get(&task.name) != Some(*task))
+            .map(|(position, task)| TrashedTask {
+                position,
+                deleted: !after.contains_key(&task.name),
+                task: task.clone(),
+            })
+            .collect();
+        let added: Vec<_> = after
+            .keys()
+            .filter(|name| !before.contains_key(*name))
+            .cloned()
+            .collect();
+        if tasks.is_empty() && added.is_empty() {
+            return;
+        }
+
+        self.batches.push(Batch {
+            at: Local::now(),
+            tasks,
+            added,
+            undo,
+        });
+        let excess = self.batches.len().saturating_sub(TRASH_LIMIT);
+        self.batches.drain(..excess);
+        self.changed = true;
+    }
+
+    /// Takes out the most recent change that wasn't itself an undo, if there
+    /// is one to undo.
+    pub fn take_last_change(&mut self) -> Option<Batch> {
+        let index = self.batches.iter().rposition(|batch| !batch.undo)?;
+        self.changed = true;
+        Some(self.batches.remove(index))
+    }
+
+    /// Takes out the latest trashed definition of `task`.
+    pub fn take(&mut self, task: &str) -> Option<TrashedTask> {
+        let (index, position) = self
+            .batches
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, batch)| {
+                let position = batch.tasks.iter().position(|t| t.task.name == task)?;
+                Some((i, position))
+            })?;
+        let trashed = self.batches[index].tasks.remove(position);
+        let batch = &self.batches[index];
+        if batch.tasks.is_empty() && batch.added.is_empty() {
+            self.batches.remove(index);
+        }
+        self.changed = true;
+        Some(trashed)
+    }
+
+    /// Writes the trash back if anything changed.
+    pub fn save(&self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for batch in &self.batches {
+            content.push_str(&serde_json::to_string(batch)?);
+            content.push('\n');
+        }
+        write_file(&trash_path()?, &content)
+    }
+}
+
+fn trash_path() -> Result<PathBuf> {
+    Ok(get_config_state_dir()?.join("trash.jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks(defs: &[(&str, &str)]) -> IndexMap<String, Task> {
+        defs.iter()
+            .map(|(name, command)| {
+                let task = Task::new(name.to_string(), "0 * * * * *".into(), command.to_string());
+                (name.to_string(), task)
+            })
+            .collect()
+    }
+
+    fn empty() -> Trash {
+        Trash {
+            batches: Vec::new(),
+            changed: false,
+        }
+    }
+
+    /// Undoes the last change in `current` the way `TaskConfig::undo` does.
+    fn undo(trash: &mut Trash, current: &mut IndexMap<String, Task>) {
+        let before = current.clone();
+        trash.take_last_change().unwrap().undo_into(current);
+        trash.record(&before, current, true);
+    }
+
+    #[test]
+    fn undoes_a_delete() {
+        let mut trash = empty();
+        let before = tasks(&[("a", "echo a"), ("b", "echo b"), ("c", "echo c")]);
+        let mut current = tasks(&[("a", "echo a"), ("c", "echo c")]);
+        trash.record(&before, &current, false);
+
+        undo(&mut trash, &mut current);
+        assert_eq!(current, before);
+        assert!(trash.take_last_change().is_none());
+    }
+
+    #[test]
+    fn undoes_an_edit() {
+        let mut trash = empty();
+        let before = tasks(&[("a", "echo a")]);
+        let mut current = tasks(&[("a", "echo edited")]);
+        trash.record(&before, &current, false);
+
+        undo(&mut trash, &mut current);
+        assert_eq!(current, before);
+        // The edited definition can still be restored.
+        assert_eq!(trash.take("a").unwrap().task.command, "echo edited");
+    }
+
+    #[test]
+    fn undo_removes_added_tasks_before_going_further_back() {
+        let mut trash = empty();
+        let original = tasks(&[("a", "echo a"), ("b", "echo b")]);
+        let deleted = tasks(&[("b", "echo b")]);
+        trash.record(&original, &deleted, false);
+        let mut current = tasks(&[("b", "echo b"), ("a", "echo b")]);
+        trash.record(&deleted, &current, false);
+
+        undo(&mut trash, &mut current);
+        assert_eq!(current, deleted);
+        undo(&mut trash, &mut current);
+        assert_eq!(current, original);
+        // The duplicate that was undone is still in the trash.
+        assert_eq!(trash.take("a").unwrap().task.command, "echo b");
+    }
+
+    #[test]
+    fn restoring_over_a_task_trashes_it() {
+        let mut trash = empty();
+        let before = tasks(&[("a", "echo old")]);
+        let mut current = tasks(&[]);
+        trash.record(&before, &current, false);
+        current = tasks(&[("a", "echo new")]);
+
+        let replaced = current.clone();
+        trash.take("a").unwrap().restore_into(&mut current);
+        trash.record(&replaced, &current, true);
+        assert_eq!(current["a"].command, "echo old");
+        assert_eq!(trash.take("a").unwrap().task.command, "echo new");
+        assert!(trash.changed);
+    }
+}
@@ -0,0 +1,713 @@
+use crate::{
+    cli::{format_duration, TaskState},
+    config::TaskConfig,
+    errors::{CronyError, Result},
+    history::{self, RunRecord},
+    state::SchedulerState,
+    task::{self, normalize_schedule, parse_schedule, Task, TaskOutcome},
+    worker,
+};
+use chrono::Local;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How many past runs and upcoming runs the detail pane lists.
+const DETAIL_RUNS: usize = 5;
+/// How often run history is read again, to pick up runs made by the
+/// scheduler.
+const HISTORY_REFRESH: Duration = Duration::from_secs(2);
+
+/// How many lines of the last run's output the detail pane shows.
+const OUTPUT_LINES: usize = 10;
+
+/// Opens the full-screen task manager and runs it until the user quits.
+pub fn run(config: TaskConfig) -> Result<()> {
+    task::set_forward_output(false);
+    let mut terminal = ratatui::try_init()?;
+    let result = App::new(config).run(&mut terminal);
+    ratatui::try_restore()?;
+    result
+}
+
+struct App {
+    config: TaskConfig,
+    names: Vec<String>,
+    list: ListState,
+    mode: Mode,
+    message: Option<(String, bool)>,
+    scheduler: Option<SchedulerState>,
+    /// The latest runs of each task, newest first, as of `history_loaded`.
+    history: HashMap<String, Vec<RunRecord>>,
+    history_loaded: Option<Instant>,
+    /// Tasks triggered from the TUI whose runs have not finished yet.
+    triggered: HashSet<String>,
+    finished: (Sender<Finished>, Receiver<Finished>),
+    /// Set after `q` is pressed while triggered runs are still going, so a
+    /// second press quits anyway.
+    confirm_quit: bool,
+    quit: bool,
+}
+
+/// A triggered run reported back to the UI thread.
+type Finished = (String, Result<RunRecord>);
+
+enum Mode {
+    Browse,
+    Form(Form),
+    ConfirmDelete(String),
+}
+
+/// The create/edit dialog. `editing` is the task being edited, or `None`
+/// when creating a new one.
+struct Form {
+    editing: Option<String>,
+    fields: Vec<Field>,
+    focus: usize,
+}
+
+struct Field {
+    kind: FieldKind,
+    value: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Name,
+    Schedule,
+    Command,
+}
+
+impl FieldKind {
+    fn label(self) -> &'static str {
+        match self {
+            FieldKind::Name => "name",
+            FieldKind::Schedule => "schedule",
+            FieldKind::Command => "command",
+        }
+    }
+}
+
+impl Form {
+    fn create() -> Self {
+        Self {
+            editing: None,
+            fields: [FieldKind::Name, FieldKind::Schedule, FieldKind::Command]
+                .into_iter()
+                .map(|kind| Field {
+                    kind,
+                    value: String::new(),
+                })
+                .collect(),
+            focus: 0,
+        }
+    }
+
+    /// Multi-step tasks only have their schedule edited here, since their
+    /// steps don't fit on a single line.
+    fn edit(task: &Task) -> Self {
+        let mut fields = vec![Field {
+            kind: FieldKind::Schedule,
+            value: task.schedule.clone(),
+        }];
+        if task.steps.is_empty() {
+            fields.push(Field {
+                kind: FieldKind::Command,
+                value: task.command.clone(),
+            });
+        }
+
+        Self {
+            editing: Some(task.name.clone()),
+            fields,
+            focus: 0,
+        }
+    }
+
+    fn value(&self, kind: FieldKind) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.kind == kind)
+            .map(|field| field.value.trim())
+    }
+}
+
+impl App {
+    fn new(config: TaskConfig) -> Self {
+        let mut app = Self {
+            config,
+            names: Vec::new(),
+            list: ListState::default(),
+            mode: Mode::Browse,
+            message: None,
+            scheduler: None,
+            history: HashMap::new(),
+            history_loaded: None,
+            triggered: HashSet::new(),
+            finished: mpsc::channel(),
+            confirm_quit: false,
+            quit: false,
+        };
+        app.refresh_names();
+        app
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            self.scheduler = SchedulerState::load()
+                .ok()
+                .flatten()
+                .filter(SchedulerState::is_live);
+            while let Ok((name, result)) = self.finished.1.try_recv() {
+                self.triggered.remove(&name);
+                self.history_loaded = None;
+                match result {
+                    Ok(record) => self.info(format!("task '{name}' finished: {}", record.outcome)),
+                    Err(e) => self.error(e),
+                }
+            }
+            if self
+                .history_loaded
+                .is_none_or(|loaded| loaded.elapsed() >= HISTORY_REFRESH)
+            {
+                self.refresh_history();
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(Duration::from_millis(250))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn refresh_names(&mut self) {
+        self.names = self.config.tasks.keys().cloned().collect();
+
+        let selected = match self.list.selected() {
+            _ if self.names.is_empty() => None,
+            Some(i) => Some(i.min(self.names.len() - 1)),
+            None => Some(0),
+        };
+        self.list.select(selected);
+        self.history_loaded = None;
+    }
+
+    fn refresh_history(&mut self) {
+        self.history = self
+            .names
+            .iter()
+            .map(|name| {
+                let runs = history::recent(name, DETAIL_RUNS).unwrap_or_default();
+                (name.clone(), runs)
+            })
+            .collect();
+        self.history_loaded = Some(Instant::now());
+    }
+
+    fn select(&mut self, name: &str) {
+        self.list
+            .select(self.names.iter().position(|other| other == name));
+    }
+
+    fn selected(&self) -> Option<&Task> {
+        let name = self.names.get(self.list.selected()?)?;
+        self.config.tasks.get(name)
+    }
+
+    fn info(&mut self, message: impl Into<String>) {
+        self.message = Some((message.into(), false));
+    }
+
+    fn error(&mut self, error: impl ToString) {
+        self.message = Some((error.to_string(), true));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => self.handle_browse_key(key),
+            Mode::Form(form) => self.handle_form_key(form, key),
+            Mode::ConfirmDelete(name) => {
+                if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    match self.config.remove_task(&name) {
+                        Ok(_) => {
+                            self.refresh_names();
+                            self.info(format!("task '{name}' deleted"));
+                        }
+                        Err(e) => self.error(e),
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        let confirm_quit = std::mem::take(&mut self.confirm_quit);
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc if self.triggered.is_empty() || confirm_quit => {
+                self.quit = true;
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.confirm_quit = true;
+                self.info(format!(
+                    "{} triggered run(s) still in progress, press q again to quit",
+                    self.triggered.len()
+                ));
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.list.select_last(),
+            KeyCode::Char('n') => self.mode = Mode::Form(Form::create()),
+            KeyCode::Char('e') => {
+                if let Some(task) = self.selected() {
+                    self.mode = Mode::Form(Form::edit(task));
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(task) = self.selected() {
+                    self.mode = Mode::ConfirmDelete(task.name.clone());
+                }
+            }
+            KeyCode::Char('p') => self.toggle_pause(),
+            KeyCode::Char('t') => self.trigger(),
+            KeyCode::Char('r') => match TaskConfig::load() {
+                Ok(config) => {
+                    self.config = config;
+                    self.refresh_names();
+                    self.info("reloaded tasks");
+                }
+                Err(e) => self.error(e),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_form_key(&mut self, mut form: Form, key: KeyEvent) {
+        let count = form.fields.len();
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter => match self.submit(&form) {
+                Ok(name) => {
+                    self.refresh_names();
+                    self.select(&name);
+                    let action = if form.editing.is_some() {
+                        "updated"
+                    } else {
+                        "created"
+                    };
+                    self.info(format!("task '{name}' {action}"));
+                    return;
+                }
+                Err(e) => self.error(e),
+            },
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % count,
+            KeyCode::BackTab | KeyCode::Up => form.focus = (form.focus + count - 1) % count,
+            KeyCode::Backspace => {
+                form.fields[form.focus].value.pop();
+            }
+            KeyCode::Char(c) => form.fields[form.focus].value.push(c),
+            _ => {}
+        }
+        self.mode = Mode::Form(form);
+    }
+
+    /// Saves the task described by `form`, returning its name.
+    fn submit(&mut self, form: &Form) -> Result<String> {
+        let schedule = form.value(FieldKind::Schedule).unwrap_or_default();
+        if !schedule.is_empty() {
+            parse_schedule(schedule)?;
+        }
+
+        let Some(name) = &form.editing else {
+            let name = form.value(FieldKind::Name).unwrap_or_default();
+            let command = form.value(FieldKind::Command).unwrap_or_default();
+            if name.is_empty() {
+                return Err(CronyError::Task("Task name cannot be empty".into()));
+            }
+            if schedule.is_empty() {
+                return Err(CronyError::Schedule("Schedule cannot be empty".into()));
+            }
+
+            let task = Task::new(name.to_string(), schedule.to_string(), command.to_string());
+            self.config.add_task(name.to_string(), task)?;
+            return Ok(name.to_string());
+        };
+
+        let current = &self.config.tasks[name];
+        let task = Task {
+            schedule: schedule.to_string(),
+            command: form
+                .value(FieldKind::Command)
+                .unwrap_or(&current.command)
+                .to_string(),
+            ..current.clone()
+        };
+        self.config.update_task(name, task)?;
+        Ok(name.clone())
+    }
+
+    fn toggle_pause(&mut self) {
+        let Some(task) = self.selected() else {
+            return;
+        };
+
+        let task = Task {
+            paused: !task.paused,
+            ..task.clone()
+        };
+        let action = if task.paused { "paused" } else { "resumed" };
+        match self.config.update_task(&task.name.clone(), task.clone()) {
+            Ok(()) => self.info(format!("task '{}' {action}", task.name)),
+            Err(e) => self.error(e),
+        }
+    }
+
+    /// Runs the selected task once in the background and records it in the
+    /// task's history.
+    fn trigger(&mut self) {
+        let Some(task) = self.selected().cloned() else {
+            return;
+        };
+        if task.paused {
+            self.error(format!("task '{}' is paused", task.name));
+            return;
+        }
+        if !self.triggered.insert(task.name.clone()) {
+            self.error(format!("task '{}' is already running", task.name));
+            return;
+        }
+
+        self.info(format!("triggered task '{}'", task.name));
+        let finished = self.finished.0.clone();
+        thread::spawn(move || {
+            let result = worker::trigger(vec![task.clone()])
+                .and_then(|mut results| results.pop().expect("one result per triggered task").1);
+            let _ = finished.send((task.name, result));
+        });
+    }
+
+    fn is_running(&self, name: &str) -> bool {
+        self.triggered.contains(name)
+            || self
+                .scheduler
+                .as_ref()
+                .is_some_and(|state| state.running(name).is_some())
+    }
+
+    /// Why the running scheduler left out `name`, if it did.
+    fn disabled(&self, name: &str) -> Option<&str> {
+        let state = self.scheduler.as_ref()?;
+        state.disabled.get(name).map(String::as_str)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, main, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+
+        frame.render_widget(self.header(), header);
+        self.draw_list(frame, list);
+        frame.render_widget(self.detail(), detail);
+        frame.render_widget(self.footer(), footer);
+
+        match &self.mode {
+            Mode::Browse => {}
+            Mode::Form(form) => draw_form(frame, form),
+            Mode::ConfirmDelete(name) => {
+                let area = popup(frame.area(), 50, 3);
+                frame.render_widget(Clear, area);
+                frame.render_widget(
+                    Paragraph::new(format!("delete task '{name}'? (y/n)"))
+                        .block(Block::bordered().title(" delete ").red()),
+                    area,
+                );
+            }
+        }
+    }
+
+    fn header(&self) -> Line<'static> {
+        let now = Local::now();
+        let scheduler = match &self.scheduler {
+            Some(state) => Span::from(format!(
+                "scheduler running (pid {}, up {})",
+                state.pid,
+                format_duration(now - state.started_at)
+            ))
+            .green(),
+            None => Span::from("scheduler not running").red(),
+        };
+        Line::from(vec![" crony ".black().on_cyan(), " ".into(), scheduler])
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<_> = self
+            .names
+            .iter()
+            .map(|name| {
+                let task = &self.config.tasks[name];
+                let last = self.history.get(name).and_then(|runs| runs.first());
+                let state = TaskState::new(
+                    task,
+                    last,
+                    self.is_running(name),
+                    self.disabled(name).is_some(),
+                );
+                ListItem::new(Line::from(vec![
+                    Span::styled("● ", Style::new().fg(state_color(state))),
+                    Span::from(name.clone()),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(" tasks "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn detail(&self) -> Paragraph<'static> {
+        let Some(task) = self.selected() else {
+            return Paragraph::new("no tasks configured, press n to create one")
+                .block(Block::bordered());
+        };
+
+        let heading = |text: &'static str| Line::from(text.bold().cyan());
+        let recent = self
+            .history
+            .get(&task.name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let disabled = self.disabled(&task.name);
+        let state = TaskState::new(
+            task,
+            recent.first(),
+            self.is_running(&task.name),
+            disabled.is_some(),
+        );
+
+        let mut lines = vec![Line::from(vec![
+            "state     ".into(),
+            Span::styled(state.label(), Style::new().fg(state_color(state))),
+        ])];
+        if let Some(reason) = disabled {
+            lines.push(Line::from(format!("          {reason}")));
+        }
+        lines.push(Line::from(format!("schedule  {}", schedule_summary(task))));
+        if !task.tags.is_empty() {
+            lines.push(Line::from(format!("tags      {}", task.tags.join(", "))));
+        }
+        for (label, names) in [
+            ("after     ", &task.depends_on),
+            ("success → ", &task.on_success),
+            ("failure → ", &task.on_failure),
+        ] {
+            if !names.is_empty() {
+                lines.push(Line::from(format!("{label}{}", names.join(", "))));
+            }
+        }
+
+        lines.push(Line::default());
+        lines.push(heading("command"));
+        for (i, step) in task.get_steps().iter().enumerate() {
+            let prefix = if task.steps.is_empty() {
+                String::new()
+            } else {
+                format!("{}. ", i + 1)
+            };
+            lines.push(Line::from(format!("  {prefix}{}", step.command)));
+        }
+
+        if !task.paused && !task.is_triggered_only() {
+            lines.push(Line::default());
+            lines.push(heading("next runs"));
+            if let (Ok(schedule), Ok(timezone)) = (task.get_schedule(), task.get_timezone()) {
+                let now = Local::now();
+                for next in schedule.upcoming(timezone).take(DETAIL_RUNS) {
+                    let next = next.with_timezone(&Local);
+                    lines.push(Line::from(format!(
+                        "  {}  in {}",
+                        next.format("%Y-%m-%d %H:%M:%S"),
+                        format_duration(next - now)
+                    )));
+                }
+            }
+        }
+
+        lines.push(Line::default());
+        lines.push(heading("recent runs"));
+        if recent.is_empty() {
+            lines.push(Line::from("  never run".dark_gray()));
+        }
+        for run in recent {
+            let exit = run
+                .exit_code()
+                .map(|code| format!("exit {code}"))
+                .unwrap_or_default();
+            lines.push(Line::from(vec![
+                format!("  {}  ", run.started_at.format("%Y-%m-%d %H:%M:%S")).into(),
+                Span::styled(
+                    format!("{:<8}", run.outcome.to_string()),
+                    Style::new().fg(outcome_color(run)),
+                ),
+                format!(
+                    "{:>8}  {exit}",
+                    format_duration(chrono::Duration::milliseconds(run.duration_ms() as i64))
+                )
+                .into(),
+            ]));
+        }
+
+        if let Some(run) = recent.first().filter(|run| !run.output_tail().is_empty()) {
+            lines.push(Line::default());
+            lines.push(heading("output of last run"));
+            let output: Vec<_> = run.output_tail().lines().collect();
+            for line in &output[output.len().saturating_sub(OUTPUT_LINES)..] {
+                lines.push(Line::from(format!("  {line}")).dark_gray());
+            }
+        }
+
+        Paragraph::new(lines)
+            .block(Block::bordered().title(format!(" {} ", task.name)))
+            .wrap(Wrap { trim: false })
+    }
+
+    fn footer(&self) -> Line<'static> {
+        match &self.message {
+            Some((message, true)) => Line::from(format!(" {message}").red()),
+            Some((message, false)) => Line::from(format!(" {message}").green()),
+            None => Line::from(
+                " ↑/↓ select  n new  e edit  d delete  p pause/resume  t trigger  r reload  q quit"
+                    .dark_gray(),
+            ),
+        }
+    }
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let area = popup(frame.area(), 70, form.fields.len() as u16 + 4);
+    let title = match &form.editing {
+        Some(name) => format!(" edit '{name}' "),
+        None => " new task ".to_string(),
+    };
+
+    let lines: Vec<_> = form
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let label = format!("{:<10}", field.kind.label());
+            let label = if i == form.focus {
+                label.bold().cyan()
+            } else {
+                label.into()
+            };
+            Line::from(vec![label, field.value.clone().into()])
+        })
+        .chain([
+            Line::default(),
+            Line::from("tab next field  enter save  esc cancel".dark_gray()),
+        ])
+        .collect();
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+
+    let field = &form.fields[form.focus];
+    frame.set_cursor_position((
+        area.x + 11 + field.value.chars().count() as u16,
+        area.y + 1 + form.focus as u16,
+    ));
+}
+
+/// A `width` by `height` area centred in `area`.
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width.min(area.width))])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height.min(area.height))])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+/// The task's schedule alongside the cron fields it restricts, e.g.
+/// `every 5 minutes (0 */5 * * * *: second 0, minute */5)`.
+fn schedule_summary(task: &Task) -> String {
+    if task.is_triggered_only() {
+        return "none, runs when triggered by another task".to_string();
+    }
+    let Ok(cron) = normalize_schedule(&task.schedule) else {
+        return format!("{} (invalid)", task.schedule);
+    };
+
+    const FIELDS: [&str; 7] = [
+        "second",
+        "minute",
+        "hour",
+        "day of month",
+        "month",
+        "day of week",
+        "year",
+    ];
+    let restricted: Vec<_> = FIELDS
+        .iter()
+        .zip(cron.split_whitespace())
+        .filter(|(_, value)| !matches!(*value, "*" | "?"))
+        .map(|(field, value)| format!("{field} {value}"))
+        .collect();
+    let explanation = match restricted.as_slice() {
+        [] => "every second".to_string(),
+        fields => fields.join(", "),
+    };
+
+    if cron == task.schedule.trim() {
+        format!("{cron} ({explanation})")
+    } else {
+        format!("{} ({cron}: {explanation})", task.schedule)
+    }
+}
+
+fn state_color(state: TaskState) -> Color {
+    match state {
+        TaskState::Idle => Color::Green,
+        TaskState::Running => Color::Cyan,
+        TaskState::Paused => Color::Yellow,
+        TaskState::Failed => Color::Red,
+        TaskState::Disabled => Color::Magenta,
+    }
+}
+
+fn outcome_color(run: &RunRecord) -> Color {
+    match run.outcome {
+        TaskOutcome::Success => Color::Green,
+        TaskOutcome::Failure | TaskOutcome::Timeout => Color::Red,
+    }
+}
@@ -0,0 +1,275 @@
+use crate::errors::{CronyError, Result};
+use crate::history::RunRecord;
+use crate::task::TaskOutcome;
+use chrono::{DateTime, Local};
+use hmac::{Hmac, Mac};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{thread, time::Duration};
+
+/// An endpoint that receives a JSON payload for task lifecycle events,
+/// configured with `[[webhooks]]`.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Webhook {
+    pub url: String,
+    /// Events to send. Defaults to all of them.
+    #[serde(default = "all_events")]
+    pub events: Vec<RunEvent>,
+    /// Shared secret used to sign the payload with HMAC-SHA256.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Extra delivery attempts after a failed request.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn all_events() -> Vec<RunEvent> {
+    vec![RunEvent::Started, RunEvent::Succeeded, RunEvent::Failed]
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunEvent {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+impl RunEvent {
+    pub fn finished(outcome: TaskOutcome) -> Self {
+        match outcome {
+            TaskOutcome::Success => RunEvent::Succeeded,
+            TaskOutcome::Failure | TaskOutcome::Timeout => RunEvent::Failed,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EventPayload {
+    pub event: RunEvent,
+    pub task: String,
+    pub run_id: String,
+    pub scheduled_at: Option<DateTime<Local>>,
+    pub started_at: DateTime<Local>,
+    pub finished_at: Option<DateTime<Local>>,
+    pub outcome: Option<TaskOutcome>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub output_tail: Option<String>,
+}
+
+impl EventPayload {
+    pub fn started(
+        task: &str,
+        run_id: &str,
+        scheduled_at: Option<DateTime<Local>>,
+        started_at: DateTime<Local>,
+    ) -> Self {
+        Self {
+            event: RunEvent::Started,
+            task: task.to_string(),
+            run_id: run_id.to_string(),
+            scheduled_at,
+            started_at,
+            finished_at: None,
+            outcome: None,
+            exit_code: None,
+            duration_ms: None,
+            output_tail: None,
+        }
+    }
+
+    pub fn finished(record: &RunRecord) -> Self {
+        Self {
+            event: RunEvent::finished(record.outcome),
+            task: record.task.clone(),
+            run_id: record.run_id.clone(),
+            scheduled_at: record.scheduled_at,
+            started_at: record.started_at,
+            finished_at: Some(record.finished_at),
+            outcome: Some(record.outcome),
+            exit_code: record.exit_code(),
+            duration_ms: Some(record.duration_ms()),
+            output_tail: Some(record.output_tail().to_string()),
+        }
+    }
+}
+
+impl Webhook {
+    /// Posts `body` for `event`, retrying with exponential backoff. Blocks
+    /// until delivery succeeds or all attempts fail.
+    pub fn send(&self, event: RunEvent, body: &str) -> Result<()> {
+        let mut headers = vec![("X-Crony-Event", event_name(event).to_string())];
+        if let Some(secret) = &self.secret {
+            headers.push((
+                "X-Crony-Signature",
+                format!("sha256={}", sign(secret, body)),
+            ));
+        }
+
+        let mut attempt = 0;
+        loop {
+            match post(&self.url, body, &headers) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(_) => {
+                    thread::sleep(Duration::from_secs(1 << attempt.min(6)));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Sends a JSON `POST` request, treating non-2xx responses as errors.
+pub fn post(url: &str, body: &str, headers: &[(&str, String)]) -> Result<()> {
+    let mut request = agent().post(url).header("Content-Type", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+
+    request
+        .send(body)
+        .map_err(|e| CronyError::Notify(format!("Request to '{url}' failed: {e}")))?;
+    Ok(())
+}
+
+/// HTTP client shared by webhooks, notifiers and healthcheck pings.
+pub fn agent() -> ureq::Agent {
+    ureq::Agent::new_with_config(
+        ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build(),
+    )
+}
+
+fn event_name(event: RunEvent) -> &'static str {
+    match event {
+        RunEvent::Started => "started",
+        RunEvent::Succeeded => "succeeded",
+        RunEvent::Failed => "failed",
+    }
+}
+
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    /// A local HTTP server that answers a request with each of `statuses` in
+    /// turn, returning its URL and, once they have been answered, the head and
+    /// body of each request.
+    pub(crate) fn stand_in(statuses: &[u16]) -> (String, JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/notify", listener.local_addr().unwrap());
+        let statuses = statuses.to_vec();
+        let handle = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut head = String::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        head.push_str(&line);
+                    }
+                    let length = header(&head, "content-length").map_or(0, |v| v.parse().unwrap());
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+                    let response =
+                        format!("HTTP/1.1 {status} Stand-in\r\nContent-Length: 0\r\n\r\n");
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                    (head, String::from_utf8(body).unwrap())
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    /// The value of the header `name` in a request head.
+    pub(crate) fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+        head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    fn webhook(url: String, secret: Option<&str>, retries: u32) -> Webhook {
+        Webhook {
+            url,
+            events: all_events(),
+            secret: secret.map(str::to_string),
+            retries,
+        }
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        assert_eq!(
+            sign("key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn sends_the_event_and_signature_headers() {
+        let (url, server) = stand_in(&[200]);
+        webhook(url, Some("s3cret"), 0)
+            .send(RunEvent::Failed, r#"{"event":"failed"}"#)
+            .unwrap();
+
+        let (head, body) = server.join().unwrap().remove(0);
+        assert_eq!(body, r#"{"event":"failed"}"#);
+        assert_eq!(header(&head, "x-crony-event"), Some("failed"));
+        let signature = format!("sha256={}", sign("s3cret", &body));
+        assert_eq!(header(&head, "x-crony-signature"), Some(signature.as_str()));
+    }
+
+    #[test]
+    fn leaves_out_the_signature_without_a_secret() {
+        let (url, server) = stand_in(&[200]);
+        webhook(url, None, 0).send(RunEvent::Started, "{}").unwrap();
+        let (head, _) = server.join().unwrap().remove(0);
+        assert_eq!(header(&head, "x-crony-signature"), None);
+    }
+
+    #[test]
+    fn retries_until_delivered() {
+        let (url, server) = stand_in(&[503, 200]);
+        webhook(url, None, 1).send(RunEvent::Started, "{}").unwrap();
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_retry() {
+        let (url, server) = stand_in(&[500]);
+        let result = webhook(url, None, 0).send(RunEvent::Started, "{}");
+        server.join().unwrap();
+        assert!(matches!(result, Err(CronyError::Notify(_))));
+    }
+}
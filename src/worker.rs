@@ -1,39 +1,349 @@
-use crate::task::{CronArgument, Task, TaskExecutor};
+use crate::config::TaskConfig;
+use crate::errors::{self, CronyError};
+use crate::healthcheck::Ping;
+use crate::history::{self, RunRecord};
+use crate::metrics::{self, Metrics};
+use crate::notify::Notifier;
+use crate::state::{RunningTask, SchedulerState, HEARTBEAT_SECS};
+use crate::task::{CronArgument, Task, TaskExecutor, TaskOutcome};
+use crate::webhook::{EventPayload, Webhook};
 use apalis::{
     layers::{retry::RetryPolicy, WorkerBuilderExt},
     prelude::{Data, WorkerBuilder, WorkerFactoryFn},
 };
-use apalis_cron::CronStream;
-use std::collections::HashMap;
-use tokio::signal;
-use tracing::{error, info};
+use apalis_cron::{CronContext, CronStream};
+use chrono::Local;
+use chrono_tz::Tz;
+use indexmap::IndexMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{signal, task::JoinHandle};
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// Runs tasks and triggers their downstream tasks once a run completes.
+pub struct Dispatcher {
+    tasks: HashMap<String, Task>,
+    notifiers: IndexMap<String, Notifier>,
+    webhooks: Vec<Webhook>,
+    metrics: Arc<Metrics>,
+    state: Mutex<SchedulerState>,
+    /// Tasks left out because of config problems, and why.
+    disabled: BTreeMap<String, String>,
+    /// For each task with `depends_on`, the dependencies that have succeeded
+    /// since it last ran.
+    satisfied: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl Dispatcher {
+    pub fn new(config: TaskConfig, disabled: BTreeMap<String, String>) -> Self {
+        let mut state = SchedulerState::current();
+        state.disabled = disabled.clone();
+        Self {
+            metrics: Arc::new(Metrics::new(&config)),
+            state: Mutex::new(state),
+            disabled,
+            tasks: config
+                .tasks
+                .into_iter()
+                .map(|(name, task)| (name, task.effective()))
+                .collect(),
+            notifiers: config.notifiers,
+            webhooks: config.webhooks,
+            satisfied: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run(self: Arc<Self>, name: String, argument: CronArgument) {
+        let span = info_span!("run", task = %name, run_id = %argument.run_id);
+        self.run_instrumented(name, argument).instrument(span).await;
+    }
+
+    async fn run_instrumented(self: Arc<Self>, name: String, argument: CronArgument) {
+        if let Some(reason) = self.disabled.get(&name) {
+            warn!("Skipping task '{name}', which is disabled: {reason}");
+            return;
+        }
+        let Some(task) = self.tasks.get(&name) else {
+            error!("Cannot run unknown task '{name}'");
+            return;
+        };
+        if task.paused {
+            info!("Skipping paused task '{name}'");
+            return;
+        }
+
+        let started = self.emit(
+            None,
+            EventPayload::started(&name, &argument.run_id, argument.scheduled_at, Local::now()),
+        );
+
+        self.metrics.started(&name);
+        self.update_state(|state| {
+            state.running.insert(
+                argument.run_id.clone(),
+                RunningTask {
+                    task: name.clone(),
+                    started_at: Local::now(),
+                },
+            );
+        });
+        let pinged = task
+            .healthcheck
+            .as_ref()
+            .map(|url| ping(None, url.clone(), Ping::Start));
+
+        let record = task.execute(argument).await;
+        info!(
+            outcome = %record.outcome,
+            exit_code = record.exit_code(),
+            duration_ms = record.duration_ms(),
+            "Task '{name}' finished"
+        );
+        self.metrics.finished(&record);
+        self.update_state(|state| {
+            state.running.remove(&record.run_id);
+        });
+        self.emit(Some(started), EventPayload::finished(&record));
+        if let Some(url) = &task.healthcheck {
+            ping(pinged, url.clone(), Ping::Finish(record.clone()));
+        }
+        if let Err(e) = history::append(&record) {
+            error!("Failed to record run of task '{name}': {e}");
+        }
+        if task
+            .notify_on
+            .as_ref()
+            .is_some_and(|on| on.contains(&record.outcome))
+        {
+            self.notify(&record);
+        }
+        self.complete(&name, record.outcome);
+    }
+
+    /// Applies `update` to the published scheduler state and saves it.
+    fn update_state(&self, update: impl FnOnce(&mut SchedulerState)) {
+        let mut state = self.state.lock().unwrap();
+        update(&mut state);
+        if let Err(e) = state.save() {
+            error!("Failed to save scheduler state: {e}");
+        }
+    }
+
+    /// Sends `payload` to the webhooks subscribed to its event once `after`
+    /// has been delivered, so each endpoint sees a run's events in order.
+    fn emit(&self, after: Option<JoinHandle<()>>, payload: EventPayload) -> JoinHandle<()> {
+        let event = payload.event;
+        let webhooks: Vec<_> = self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.events.contains(&event))
+            .cloned()
+            .collect();
 
-pub async fn create_worker(task: Task) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tokio::spawn(async move {
+            if let Some(after) = after {
+                let _ = after.await;
+            }
+            if webhooks.is_empty() {
+                return;
+            }
+
+            let body = match serde_json::to_string(&payload) {
+                Ok(body) => body,
+                Err(e) => {
+                    error!("Failed to serialize webhook payload: {e}");
+                    return;
+                }
+            };
+            let _ = tokio::task::spawn_blocking(move || {
+                for webhook in webhooks {
+                    if let Err(e) = webhook.send(event, &body) {
+                        error!(
+                            "Failed to deliver webhook to '{}' for task '{}': {e}",
+                            webhook.url, payload.task
+                        );
+                    }
+                }
+            })
+            .await;
+        })
+    }
+
+    fn notify(&self, record: &RunRecord) {
+        for (notifier_name, notifier) in &self.notifiers {
+            let notifier_name = notifier_name.clone();
+            let notifier = notifier.clone();
+            let record = record.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = notifier.send(&record) {
+                    error!(
+                        "Failed to notify '{notifier_name}' about task '{}': {e}",
+                        record.task
+                    );
+                }
+            });
+        }
+    }
+
+    fn complete(self: &Arc<Self>, name: &str, outcome: TaskOutcome) {
+        let task = &self.tasks[name];
+        let mut triggered = match outcome {
+            TaskOutcome::Success => task.on_success.clone(),
+            TaskOutcome::Failure | TaskOutcome::Timeout => task.on_failure.clone(),
+        };
+
+        {
+            let mut satisfied = self.satisfied.lock().unwrap();
+            for (dependant_name, dependant) in &self.tasks {
+                if !dependant.depends_on.iter().any(|d| d == name) {
+                    continue;
+                }
+
+                let done = satisfied.entry(dependant_name.clone()).or_default();
+                match outcome {
+                    TaskOutcome::Success => {
+                        done.insert(name.to_string());
+                        if dependant.depends_on.iter().all(|d| done.contains(d)) {
+                            done.clear();
+                            triggered.push(dependant_name.clone());
+                        }
+                    }
+                    TaskOutcome::Failure | TaskOutcome::Timeout => {
+                        done.remove(name);
+                    }
+                }
+            }
+        }
+
+        for next in triggered {
+            info!("Task '{name}' triggered task '{next}'");
+            tokio::spawn(Arc::clone(self).run(next, CronArgument::triggered()));
+        }
+    }
+}
+
+/// Sends `ping` to the healthcheck at `url` once `after` has been delivered.
+fn ping(after: Option<JoinHandle<()>>, url: String, ping: Ping) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Some(after) = after {
+            let _ = after.await;
+        }
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Err(e) = ping.send(&url) {
+                error!("Failed to ping healthcheck: {e}");
+            }
+        })
+        .await;
+    })
+}
+
+/// Runs each task once, concurrently and outside the scheduler, and records
+/// the runs in history. Downstream tasks, webhooks and notifications are left
+/// to the scheduler. Blocks until every run has finished.
+pub fn trigger(tasks: Vec<Task>) -> errors::Result<Vec<(String, errors::Result<RunRecord>)>> {
+    let rt = tokio::runtime::Runtime::new()
+        .map_err(|e| CronyError::Task(format!("Failed to create runtime: {e}")))?;
+
+    Ok(rt.block_on(async {
+        let runs: Vec<_> = tasks
+            .into_iter()
+            .map(|task| {
+                let name = task.name.clone();
+                (name, tokio::spawn(run_once(task)))
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for (name, run) in runs {
+            let result = run.await.unwrap_or_else(|e| {
+                Err(CronyError::Task(format!(
+                    "Run of task '{name}' panicked: {e}"
+                )))
+            });
+            results.push((name, result));
+        }
+        results
+    }))
+}
+
+async fn run_once(task: Task) -> errors::Result<RunRecord> {
+    let record = task.execute(CronArgument::triggered()).await;
+    history::append(&record)?;
+    Ok(record)
+}
+
+pub async fn create_worker(
+    task: Task,
+    dispatcher: Arc<Dispatcher>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let schedule = task
         .get_schedule()
         .map_err(|e| format!("Invalid schedule for task '{}': {e}", task.name))?;
+    let timezone = task.get_timezone()?;
 
     let worker = WorkerBuilder::new(task.name.clone())
         .retry(RetryPolicy::default())
         .data(task.clone())
-        .backend(CronStream::new(schedule))
+        .data(dispatcher)
+        .backend(CronStream::new_with_timezone(schedule, timezone))
         .build_fn(perform_task);
 
     worker.run().await;
     Ok(())
 }
 
-pub async fn run_all_tasks(tasks: HashMap<String, Task>) {
-    if tasks.is_empty() {
+/// Schedules every task in `config` until crony is stopped. `disabled` lists
+/// the tasks left out of `config` because of problems, for `crony status`.
+pub async fn run_all_tasks(
+    config: TaskConfig,
+    metrics_addr: Option<SocketAddr>,
+    disabled: BTreeMap<String, String>,
+) {
+    if config.is_empty() {
         return;
     }
 
+    let tasks = config.tasks.clone();
+    let dispatcher = Arc::new(Dispatcher::new(config, disabled));
     let mut handles = Vec::new();
 
+    dispatcher.update_state(|_| {});
+    let heartbeat = Arc::clone(&dispatcher);
+    handles.push(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(HEARTBEAT_SECS as u64));
+        loop {
+            interval.tick().await;
+            heartbeat.update_state(|_| {});
+        }
+    }));
+
+    if let Some(addr) = metrics_addr {
+        let metrics = Arc::clone(&dispatcher.metrics);
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr, metrics).await {
+                error!("Failed to serve metrics on {addr}: {e}");
+            }
+        }));
+    }
+
     for (name, task) in tasks {
+        if task.paused {
+            info!("Task '{name}' is paused");
+            continue;
+        }
+        if task.is_triggered_only() {
+            info!("Task '{name}' has no schedule and only runs when triggered");
+            continue;
+        }
+
+        let dispatcher = Arc::clone(&dispatcher);
         let task_handle = tokio::spawn(async move {
             info!("Starting task: {name}");
-            if let Err(e) = create_worker(task).await {
+            if let Err(e) = create_worker(task, dispatcher).await {
                 error!("Error running task '{name}': {e}");
             }
         });
@@ -70,9 +380,21 @@ pub async fn run_all_tasks(tasks: HashMap<String, Task>) {
         handle.abort();
     }
 
+    if let Err(e) = SchedulerState::clear() {
+        error!("Failed to clear scheduler state: {e}");
+    }
+
     info!("All tasks stopped.");
 }
 
-pub async fn perform_task(job: CronArgument, data: Data<Task>) {
-    data.execute(job).await;
+pub async fn perform_task(
+    _job: CronArgument,
+    context: CronContext<Tz>,
+    task: Data<Task>,
+    dispatcher: Data<Arc<Dispatcher>>,
+) {
+    let argument = CronArgument::from(context.get_timestamp().with_timezone(&Local));
+    Arc::clone(&dispatcher)
+        .run(task.name.clone(), argument)
+        .await;
 }
@@ -0,0 +1,71 @@
+use std::{
+    env, fs,
+    process::{Command, Stdio},
+};
+
+#[test]
+fn concurrent_changes_are_all_kept() {
+    let dir = env::temp_dir().join(format!("crony-config-cli-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("tasks.toml");
+    fs::write(
+        &config,
+        "version = 1\n\n[tasks.a]\nname = \"a\"\nschedule = \"0 0 * * * *\"\ncommand = \"true\"\n",
+    )
+    .unwrap();
+
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            Command::new(env!("CARGO_BIN_EXE_crony"))
+                .arg("--config")
+                .arg(&config)
+                .args(["tag", &format!("t{i}"), "a"])
+                .env("XDG_STATE_HOME", dir.join("state"))
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let content = fs::read_to_string(&config).unwrap();
+    for i in 0..8 {
+        assert!(
+            content.contains(&format!("\"t{i}\"")),
+            "t{i} is missing:\n{content}"
+        );
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn list_json_stays_clean_when_the_config_is_upgraded() {
+    let dir = env::temp_dir().join(format!("crony-list-cli-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("tasks.toml");
+    // No version key, so crony upgrades the file and logs that it did.
+    fs::write(
+        &config,
+        "[tasks.a]\nname = \"a\"\nschedule = \"0 0 * * * *\"\ncommand = \"true\"\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_crony"))
+        .arg("--config")
+        .arg(&config)
+        .args(["list", "--format", "json"])
+        .env("XDG_STATE_HOME", dir.join("state"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rows[0]["name"], "a");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Upgraded"), "{stderr}");
+    assert!(!stderr.contains('\x1b'), "{stderr}");
+    fs::remove_dir_all(dir).unwrap();
+}
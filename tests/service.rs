@@ -0,0 +1,61 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+fn crony(home: &PathBuf, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_crony"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CRONY_CONFIG")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn service_install_and_uninstall_write_into_dir() {
+    let home = env::temp_dir().join(format!("crony-service-cli-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&home);
+    let dir = home.join("units");
+    let dir_arg = dir.to_str().unwrap();
+    let unit_path = dir.join("crony.service");
+
+    let stdout = crony(&home, &["service", "install", "--dir", dir_arg]);
+    assert!(stdout.contains(&format!("installed {}\n", unit_path.display())));
+    assert!(stdout.contains("systemctl --user enable --now crony.service"));
+    let unit = fs::read_to_string(&unit_path).unwrap();
+    let config = home.join(".config/crony/tasks.toml");
+    assert!(unit.contains(&format!("--config {} run", config.display())));
+    assert!(unit.contains("WantedBy=default.target"));
+
+    let stdout = crony(&home, &["service", "install", "--dir", dir_arg]);
+    assert!(stdout.contains(&format!("{} is already up to date\n", unit_path.display())));
+
+    let stdout = crony(&home, &["service", "status", "--dir", dir_arg]);
+    let status = format!("unit {} installed", unit_path.display());
+    assert_eq!(stdout.lines().next(), Some(status.as_str()));
+    assert!(!stdout.contains("not installed") && !stdout.contains("out of date"));
+
+    fs::write(&unit_path, unit.replace("RestartSec=5", "RestartSec=9")).unwrap();
+    let stdout = crony(&home, &["service", "status", "--dir", dir_arg]);
+    assert!(stdout.contains("out of date"), "{stdout}");
+
+    // `systemctl enable` links the unit into the target's wants directory.
+    let wants = dir.join("default.target.wants");
+    fs::create_dir_all(&wants).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&unit_path, wants.join("crony.service")).unwrap();
+
+    let stdout = crony(&home, &["service", "uninstall", "--dir", dir_arg]);
+    assert!(stdout.contains(&format!("removed {}\n", unit_path.display())));
+    assert!(!unit_path.exists());
+    assert!(wants.join("crony.service").symlink_metadata().is_err());
+
+    let stdout = crony(&home, &["service", "status", "--dir", dir_arg]);
+    let status = format!("unit {} not installed", unit_path.display());
+    assert_eq!(stdout.lines().next(), Some(status.as_str()));
+    let stdout = crony(&home, &["service", "uninstall", "--dir", dir_arg]);
+    assert!(stdout.contains("isn't installed"));
+
+    fs::remove_dir_all(home).unwrap();
+}